@@ -0,0 +1,575 @@
+use crate::chunk::{Chunk, Constant, Function, OpCode};
+use crate::token::Span;
+use std::rc::Rc;
+
+/// The first bytes of every precompiled file.
+const MAGIC: &[u8; 4] = b"LOXC";
+
+/// Bumped whenever the layout below or the meaning of an opcode changes, so
+/// files compiled by an older interpreter are rejected instead of misread.
+const VERSION: u16 = 1;
+
+const HEADER_LEN: usize = MAGIC.len() + 2 + 4;
+
+/// How deeply functions may be declared inside one another. Nested functions
+/// are read recursively, so a crafted file could otherwise exhaust the stack.
+const MAX_NESTING: usize = 256;
+
+const NUMBER: u8 = 0;
+const STRING: u8 = 1;
+const FUNCTION: u8 = 2;
+
+/// Serializes a compiled script. The source is stored along with the code so
+/// diagnostics from the loaded program can still quote it; `offset` is where
+/// the source starts in the spans of `function`.
+///
+/// The layout is the magic number, the format version and a CRC-32 of the
+/// payload, followed by the payload: the source name and text, then the
+/// script function. A function is its name, arity, upvalue count, code, a
+/// run-length encoded table of source locations and its constants, with
+/// nested functions stored inline. Integers are little-endian.
+pub fn encode(function: &Function, name: &str, source: &str, offset: usize) -> Vec<u8> {
+    let mut payload = Vec::new();
+    write_str(&mut payload, name);
+    write_str(&mut payload, source);
+    write_function(&mut payload, function, offset);
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Checks and deserializes a file written by [`encode`]. Once the file has
+/// been accepted, `register` is given the stored source name and text and
+/// returns the offset the loaded spans should start at.
+pub fn decode(
+    bytes: &[u8],
+    register: impl FnOnce(&str, &str) -> usize,
+) -> Result<Function, String> {
+    if bytes.len() < HEADER_LEN || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(String::from("Not a compiled Lox file."));
+    }
+
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != VERSION {
+        return Err(format!(
+            "Compiled for bytecode version {} but this interpreter runs version {}; recompile the script.",
+            version, VERSION
+        ));
+    }
+
+    let checksum = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+    let payload = &bytes[HEADER_LEN..];
+    if crc32(payload) != checksum {
+        return Err(String::from("Checksum mismatch; the file is corrupt."));
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        position: 0,
+    };
+    let name = reader.string()?;
+    let source = reader.string()?;
+    let mut function = reader.function(0)?;
+    if function.arity != 0 || function.upvalue_count != 0 {
+        return Err(String::from(
+            "The compiled script takes arguments or captures variables.",
+        ));
+    }
+
+    if reader.position != payload.len() {
+        return Err(String::from("Unexpected data after the compiled script."));
+    }
+
+    let offset = register(&name, &source);
+    shift_spans(&mut function, offset);
+    Ok(function)
+}
+
+/// Moves the spans of `function` and the functions nested in it, which are
+/// read relative to the start of their source, to where it was registered.
+fn shift_spans(function: &mut Function, offset: usize) {
+    for span in &mut function.chunk.spans {
+        *span = Span::new(span.start + offset, span.end + offset);
+    }
+    for constant in &mut function.chunk.constants {
+        if let Constant::Function(nested) = constant {
+            let nested = Rc::get_mut(nested).expect("a loaded function is not shared yet");
+            shift_spans(nested, offset);
+        }
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: usize) {
+    bytes.extend_from_slice(&(value as u32).to_le_bytes());
+}
+
+fn write_str(bytes: &mut Vec<u8>, value: &str) {
+    write_u32(bytes, value.len());
+    bytes.extend_from_slice(value.as_bytes());
+}
+
+fn write_function(bytes: &mut Vec<u8>, function: &Function, offset: usize) {
+    write_str(bytes, &function.name);
+    write_u32(bytes, function.arity);
+    write_u32(bytes, function.upvalue_count);
+
+    let chunk = &function.chunk;
+    write_u32(bytes, chunk.code.len());
+    bytes.extend_from_slice(&chunk.code);
+
+    // Every byte of an instruction shares its location, so store runs.
+    let locations: Vec<_> = chunk.lines.iter().zip(&chunk.spans).collect();
+    let runs: Vec<_> = locations.chunk_by(|a, b| a == b).collect();
+    write_u32(bytes, runs.len());
+    for run in runs {
+        let (line, span) = run[0];
+        write_u32(bytes, run.len());
+        write_u32(bytes, *line);
+        // Code emitted before the first token, such as a leading literal, has
+        // the empty span at 0 rather than one inside this source.
+        write_u32(bytes, span.start.saturating_sub(offset));
+        write_u32(bytes, span.end.saturating_sub(offset));
+    }
+
+    write_u32(bytes, chunk.constants.len());
+    for constant in &chunk.constants {
+        match constant {
+            Constant::Number(n) => {
+                bytes.push(NUMBER);
+                bytes.extend_from_slice(&n.to_le_bytes());
+            }
+            Constant::String(s) => {
+                bytes.push(STRING);
+                write_str(bytes, s);
+            }
+            Constant::Function(function) => {
+                bytes.push(FUNCTION);
+                write_function(bytes, function, offset);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self.position.saturating_add(len);
+        let Some(bytes) = self.bytes.get(self.position..end) else {
+            return Err(String::from("The file ends unexpectedly."));
+        };
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        let bytes = self.take(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| String::from("Invalid UTF-8 in a string."))
+    }
+
+    fn function(&mut self, depth: usize) -> Result<Function, String> {
+        if depth > MAX_NESTING {
+            return Err(String::from("Functions are nested too deeply."));
+        }
+
+        let name = self.string()?;
+        let arity = self.u32()?;
+        let upvalue_count = self.u32()?;
+
+        let mut chunk = Chunk::default();
+        let len = self.u32()?;
+        chunk.code = self.take(len)?.to_vec();
+
+        let runs = self.u32()?;
+        for _ in 0..runs {
+            let count = self.u32()?;
+            if count > chunk.code.len() - chunk.lines.len() {
+                return Err(format!(
+                    "The line table of '{}' does not match its code.",
+                    name
+                ));
+            }
+            let line = self.u32()?;
+            let span = Span::new(self.u32()?, self.u32()?);
+            chunk.lines.extend(std::iter::repeat_n(line, count));
+            chunk.spans.extend(std::iter::repeat_n(span, count));
+        }
+        if chunk.lines.len() != chunk.code.len() {
+            return Err(format!(
+                "The line table of '{}' does not match its code.",
+                name
+            ));
+        }
+
+        let constants = self.u32()?;
+        for _ in 0..constants {
+            let constant = match self.u8()? {
+                NUMBER => Constant::Number(self.f64()?),
+                STRING => Constant::String(Rc::from(self.string()?)),
+                FUNCTION => Constant::Function(Rc::new(self.function(depth + 1)?)),
+                tag => return Err(format!("Unknown constant tag {}.", tag)),
+            };
+            chunk.add_constant(constant);
+        }
+
+        let function = Function {
+            name: Rc::from(name),
+            arity,
+            upvalue_count,
+            chunk,
+        };
+        check_code(&function)?;
+        Ok(function)
+    }
+}
+
+/// Checks that every instruction of `function` is whole, that its operands
+/// name constants of the right kind and upvalues the function has, and that
+/// every path through the code jumps to instructions, never pops more than
+/// the stack holds and only reads slots below its top. The checksum only
+/// catches accidental damage, so this keeps a crafted file from making the VM
+/// read outside what it was given.
+fn check_code(function: &Function) -> Result<(), String> {
+    let chunk = &function.chunk;
+    let invalid = |offset: usize, problem: &str| {
+        format!(
+            "Invalid code in '{}' at offset {}: {}.",
+            function.name, offset, problem
+        )
+    };
+
+    // The end of the instruction starting at each offset.
+    let mut ends = vec![None; chunk.code.len()];
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+            return Err(invalid(
+                offset,
+                &format!("unknown opcode {}", chunk.code[offset]),
+            ));
+        };
+
+        let operands = match op {
+            OpCode::Call => 1,
+            OpCode::Class => 5,
+            OpCode::Constant
+            | OpCode::GetLocal
+            | OpCode::SetLocal
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetUpvalue
+            | OpCode::SetUpvalue
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Interpolate
+            | OpCode::Jump
+            | OpCode::JumpIfFalse
+            | OpCode::Loop
+            | OpCode::Closure
+            | OpCode::PushCatch
+            | OpCode::PushFinally => 2,
+            _ => 0,
+        };
+        let mut end = offset + 1 + operands;
+        if end > chunk.code.len() {
+            return Err(invalid(offset, "the instruction is cut off"));
+        }
+
+        let constant = || chunk.constants.get(chunk.read_u16(offset + 1) as usize);
+        match op {
+            OpCode::Constant
+                if !matches!(constant(), Some(Constant::Number(_) | Constant::String(_))) =>
+            {
+                return Err(invalid(offset, "expected a number or string constant"));
+            }
+            OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Class
+                if !matches!(constant(), Some(Constant::String(_))) =>
+            {
+                return Err(invalid(offset, "expected a name constant"));
+            }
+            OpCode::GetUpvalue | OpCode::SetUpvalue
+                if chunk.read_u16(offset + 1) as usize >= function.upvalue_count =>
+            {
+                return Err(invalid(offset, "no such upvalue"));
+            }
+            OpCode::Closure => {
+                let Some(Constant::Function(closed)) = constant() else {
+                    return Err(invalid(offset, "expected a function constant"));
+                };
+                for _ in 0..closed.upvalue_count {
+                    if end + 3 > chunk.code.len() {
+                        return Err(invalid(offset, "the instruction is cut off"));
+                    }
+                    let is_local = chunk.code[end] == 1;
+                    if !is_local && chunk.read_u16(end + 1) as usize >= function.upvalue_count {
+                        return Err(invalid(offset, "no such upvalue"));
+                    }
+                    end += 3;
+                }
+            }
+            _ => {}
+        }
+        ends[offset] = Some(end);
+        offset = end;
+    }
+
+    // Follow every path from the entry with the height of the stack above
+    // the frame's base, which starts with the callee and its arguments.
+    let mut heights = vec![None; chunk.code.len()];
+    let mut paths = vec![(0, 0, function.arity + 1)];
+    while let Some((from, offset, height)) = paths.pop() {
+        if offset >= chunk.code.len() {
+            return Err(invalid(from, "the code runs past its end"));
+        }
+        let Some(end) = ends[offset] else {
+            return Err(invalid(from, "the jump does not land on an instruction"));
+        };
+        match heights[offset] {
+            Some(seen) if seen == height => continue,
+            Some(_) => return Err(invalid(offset, "the stack height depends on the path")),
+            None => heights[offset] = Some(height),
+        }
+
+        let op = OpCode::from_byte(chunk.code[offset]).unwrap();
+        let operand = || chunk.read_u16(offset + 1) as usize;
+        let (pops, pushes) = match op {
+            OpCode::Constant
+            | OpCode::Nil
+            | OpCode::True
+            | OpCode::False
+            | OpCode::GetGlobal
+            | OpCode::GetLocal
+            | OpCode::GetUpvalue
+            | OpCode::Closure => (0, 1),
+            OpCode::Pop
+            | OpCode::DefineGlobal
+            | OpCode::Print
+            | OpCode::CloseUpvalue
+            | OpCode::CheckSuperclass
+            | OpCode::Return
+            | OpCode::Throw => (1, 0),
+            OpCode::SetLocal
+            | OpCode::SetGlobal
+            | OpCode::SetUpvalue
+            | OpCode::GetProperty
+            | OpCode::CheckInstance
+            | OpCode::Not
+            | OpCode::Negate
+            | OpCode::JumpIfFalse => (1, 1),
+            OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Equal
+            | OpCode::Greater
+            | OpCode::GreaterEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide => (2, 1),
+            OpCode::Interpolate => (operand(), 1),
+            OpCode::Call => (chunk.code[offset + 1] as usize + 1, 1),
+            // A superclass is read from beneath the methods but left there.
+            OpCode::Class if chunk.code[offset + 5] == 1 => {
+                let count = chunk.read_u16(offset + 3) as usize;
+                (count + 1, 2)
+            }
+            OpCode::Class => (chunk.read_u16(offset + 3) as usize, 1),
+            OpCode::Jump
+            | OpCode::Loop
+            | OpCode::PushCatch
+            | OpCode::PushFinally
+            | OpCode::PopHandler
+            | OpCode::Rethrow => (0, 0),
+        };
+        if pops > height {
+            return Err(invalid(offset, "the stack would underflow"));
+        }
+        if matches!(op, OpCode::GetLocal | OpCode::SetLocal) && operand() >= height {
+            return Err(invalid(offset, "no such local"));
+        }
+        // A local function captures itself from the slot it is about to fill.
+        if op == OpCode::Closure {
+            let mut captures = offset + 3;
+            while captures < end {
+                if chunk.code[captures] == 1 && chunk.read_u16(captures + 1) as usize > height {
+                    return Err(invalid(offset, "no such local"));
+                }
+                captures += 3;
+            }
+        }
+
+        let next = height - pops + pushes;
+        match op {
+            OpCode::Return | OpCode::Throw | OpCode::Rethrow => {}
+            OpCode::Jump => paths.push((offset, end + operand(), next)),
+            OpCode::Loop => match end.checked_sub(operand()) {
+                Some(target) => paths.push((offset, target, next)),
+                None => return Err(invalid(offset, "the jump leaves the function")),
+            },
+            OpCode::JumpIfFalse => {
+                paths.push((offset, end + operand(), next));
+                paths.push((offset, end, next));
+            }
+            // A handler resumes with the error, or a placeholder for it, on
+            // top of the stack as it was when the handler was installed.
+            OpCode::PushCatch | OpCode::PushFinally => {
+                paths.push((offset, end + operand(), height + 1));
+                paths.push((offset, end, next));
+            }
+            _ => paths.push((offset, end, next)),
+        }
+    }
+    Ok(())
+}
+
+/// The CRC-32 used by zip and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crc32_matches_the_standard_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    /// A script that returns `nil`.
+    fn script() -> Function {
+        let mut function = Function {
+            name: Rc::from("script"),
+            ..Function::default()
+        };
+        function.chunk.write(OpCode::Nil as u8, 1, Span::default());
+        function
+            .chunk
+            .write(OpCode::Return as u8, 1, Span::default());
+        function
+    }
+
+    #[test]
+    fn test_rejects_foreign_stale_and_corrupt_files() {
+        let bytes = encode(&script(), "a.lox", "", 0);
+        assert!(decode(&bytes, |_, _| 0).is_ok());
+
+        assert_eq!(
+            decode(b"#!/usr/bin/env lox", |_, _| 0).unwrap_err(),
+            "Not a compiled Lox file."
+        );
+
+        let mut stale = bytes.clone();
+        stale[4] = 0;
+        assert!(decode(&stale, |_, _| 0).unwrap_err().contains("recompile"));
+
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            decode(&corrupt, |_, _| 0).unwrap_err(),
+            "Checksum mismatch; the file is corrupt."
+        );
+    }
+
+    #[test]
+    fn test_registers_the_source_only_once_the_file_is_accepted() {
+        let mut function = script();
+        function.chunk.spans = vec![Span::new(2, 3); 2];
+        let bytes = encode(&function, "a.lox", "print;", 0);
+
+        let mut registered = Vec::new();
+        let loaded = decode(&bytes, |name, _| {
+            registered.push(name.to_string());
+            10
+        })
+        .unwrap();
+        assert_eq!(registered, ["a.lox"]);
+        assert_eq!(loaded.chunk.spans, vec![Span::new(12, 13); 2]);
+
+        let mut truncated = bytes[..bytes.len() - 1].to_vec();
+        let checksum = crc32(&truncated[HEADER_LEN..]);
+        truncated[6..HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+        assert!(decode(&truncated, |_, _| panic!("registered a rejected file")).is_err());
+    }
+
+    #[test]
+    fn test_rejects_bad_code_even_with_a_matching_checksum() {
+        let mut bytes = encode(&script(), "a.lox", "", 0);
+        // The code follows the source name and text, then the function's
+        // name, arity, upvalue count and code length.
+        let code = HEADER_LEN + (4 + 5) + 4 + (4 + 6) + 4 + 4 + 4;
+        assert_eq!(bytes[code], OpCode::Nil as u8);
+
+        bytes[code] = 200;
+        let checksum = crc32(&bytes[HEADER_LEN..]);
+        bytes[6..HEADER_LEN].copy_from_slice(&checksum.to_le_bytes());
+        assert_eq!(
+            decode(&bytes, |_, _| 0).unwrap_err(),
+            "Invalid code in 'script' at offset 0: unknown opcode 200."
+        );
+    }
+
+    #[test]
+    fn test_rejects_deeply_nested_functions() {
+        // Each function has no code or locations and one constant: the next.
+        let mut payload = Vec::new();
+        write_str(&mut payload, "a.lox");
+        write_str(&mut payload, "");
+        for _ in 0..100_000 {
+            write_str(&mut payload, "f");
+            for count in [0, 0, 0, 0, 1] {
+                write_u32(&mut payload, count);
+            }
+            payload.push(FUNCTION);
+        }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+        bytes.extend_from_slice(&payload);
+        assert_eq!(
+            decode(&bytes, |_, _| 0).unwrap_err(),
+            "Functions are nested too deeply."
+        );
+    }
+}
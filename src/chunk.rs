@@ -0,0 +1,179 @@
+use crate::token::Span;
+use std::rc::Rc;
+
+/// An instruction of the bytecode VM. Operands follow the opcode in the code
+/// stream; indexes and jump distances are 16-bit big-endian unless noted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    /// Pushes a constant: `index`.
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    /// Variable ops on globals take the index of the name constant.
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetUpvalue,
+    SetUpvalue,
+    GetProperty,
+    SetProperty,
+    /// Fails unless the object under the top of the stack is an instance,
+    /// so a bad property assignment is reported before its value runs.
+    CheckInstance,
+    /// Looks a method up on the superclass on top of the stack and binds it
+    /// to the receiver beneath: `name`.
+    GetSuper,
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    /// Stringifies and concatenates the top `count` values.
+    Interpolate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    /// Jumps backwards by `distance`.
+    Loop,
+    /// Calls the value under the arguments: `count` (8-bit).
+    Call,
+    /// Creates a closure over a function constant: `index`, then an 8-bit
+    /// is-local flag and a slot or upvalue index for each captured variable.
+    Closure,
+    CloseUpvalue,
+    Return,
+    /// Fails unless the top of the stack is a class, then pops it.
+    CheckSuperclass,
+    /// Builds a class from method closures on the stack: `name`, `count`, and
+    /// an 8-bit flag for whether the superclass sits beneath them.
+    Class,
+    Throw,
+    /// Installs a handler that binds the error and resumes at `distance`
+    /// ahead.
+    PushCatch,
+    /// Installs a handler that runs a `finally` block at `distance` ahead and
+    /// then raises the error again.
+    PushFinally,
+    PopHandler,
+    /// Raises the error a `finally` handler set aside.
+    Rethrow,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 43] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::GetProperty,
+        OpCode::SetProperty,
+        OpCode::CheckInstance,
+        OpCode::GetSuper,
+        OpCode::Equal,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Interpolate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+        OpCode::CheckSuperclass,
+        OpCode::Class,
+        OpCode::Throw,
+        OpCode::PushCatch,
+        OpCode::PushFinally,
+        OpCode::PopHandler,
+        OpCode::Rethrow,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        OpCode::ALL.get(byte as usize).copied()
+    }
+}
+
+/// A value known at compile time.
+#[derive(Debug, Clone)]
+pub enum Constant {
+    Number(f64),
+    String(Rc<str>),
+    Function(Rc<Function>),
+}
+
+/// A compiled function body: its code, the constants it refers to, and the
+/// source location of every byte of code.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Constant>,
+    pub lines: Vec<usize>,
+    pub spans: Vec<Span>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, line: usize, span: Span) {
+        self.code.push(byte);
+        self.lines.push(line);
+        self.spans.push(span);
+    }
+
+    pub fn add_constant(&mut self, constant: Constant) -> usize {
+        self.constants.push(constant);
+        self.constants.len() - 1
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Function {
+    pub name: Rc<str>,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    pub chunk: Chunk,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opcodes_round_trip_through_bytes() {
+        for op in OpCode::ALL {
+            assert_eq!(OpCode::from_byte(op as u8), Some(op));
+        }
+        assert_eq!(OpCode::from_byte(OpCode::ALL.len() as u8), None);
+    }
+}
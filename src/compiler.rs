@@ -0,0 +1,809 @@
+use crate::Lox;
+use crate::chunk::{Constant, Function, OpCode};
+use crate::diagnostic::Diagnostic;
+use crate::error_code::ErrorCode;
+use crate::expression::{Expr, FunctionDecl, LiteralValue, Stmt, StmtVisitor, Visitor};
+use crate::token::{Span, Token, TokenType};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    Script,
+    Function,
+    Initializer,
+    Method,
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Upvalue {
+    index: u16,
+    is_local: bool,
+}
+
+/// A `try` statement whose body or `catch` block is being compiled.
+#[derive(Clone)]
+struct TryRegion {
+    /// How many of the statement's handlers are installed at this point.
+    handlers: usize,
+    /// Run on the way out of a `return`.
+    finally: Option<Vec<Stmt>>,
+}
+
+/// The function currently being compiled, with its locals laid out in the
+/// same order they will occupy its stack window.
+struct FunctionState {
+    function: Function,
+    function_type: FunctionType,
+    locals: Vec<Local>,
+    upvalues: Vec<Upvalue>,
+    scope_depth: usize,
+    tries: Vec<TryRegion>,
+    names: HashMap<String, u16>,
+}
+
+/// Compiles a resolved AST into bytecode for the VM. Like the resolver, it
+/// reports problems through [`Lox`] and leaves it to the caller to check
+/// `had_error`.
+pub struct Compiler<'a> {
+    lox: &'a mut Lox,
+    states: Vec<FunctionState>,
+    line: usize,
+    span: Span,
+}
+
+impl<'a> Compiler<'a> {
+    pub fn new(lox: &'a mut Lox) -> Compiler<'a> {
+        Compiler {
+            lox,
+            states: Vec::new(),
+            line: 1,
+            span: Span::default(),
+        }
+    }
+
+    /// Compiles a script into a function that returns the value of its last
+    /// statement if that is an expression, as the tree-walker does.
+    pub fn compile(mut self, statements: &[Stmt]) -> Function {
+        self.begin_function("script", FunctionType::Script);
+
+        match statements.split_last() {
+            Some((Stmt::Expression(last), rest)) => {
+                self.statements(rest);
+                self.expression(last);
+                self.emit_op(OpCode::Return);
+            }
+            _ => {
+                self.statements(statements);
+                self.emit_return();
+            }
+        }
+
+        self.end_function().0
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn block(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.statements(statements);
+        self.end_scope();
+    }
+
+    fn function(&mut self, declaration: &FunctionDecl, function_type: FunctionType) {
+        self.set_location(&declaration.name);
+        self.begin_function(&declaration.name.lexeme, function_type);
+        self.begin_scope();
+
+        for param in &declaration.params {
+            self.add_local(&param.lexeme);
+        }
+        self.state().function.arity = declaration.params.len();
+        self.statements(&declaration.body);
+        self.emit_return();
+
+        let (function, upvalues) = self.end_function();
+        self.set_location(&declaration.name);
+        let index = self.make_constant(Constant::Function(Rc::new(function)));
+        self.emit_op(OpCode::Closure);
+        self.emit_u16(index);
+        for upvalue in upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_u16(upvalue.index);
+        }
+    }
+
+    fn begin_function(&mut self, name: &str, function_type: FunctionType) {
+        // Slot zero holds the receiver in methods and the callee otherwise.
+        let slot_zero = match function_type {
+            FunctionType::Initializer | FunctionType::Method => "this",
+            FunctionType::Function | FunctionType::Script => "",
+        };
+
+        self.states.push(FunctionState {
+            function: Function {
+                name: Rc::from(name),
+                ..Function::default()
+            },
+            function_type,
+            locals: vec![Local {
+                name: slot_zero.to_string(),
+                depth: 0,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            tries: Vec::new(),
+            names: HashMap::new(),
+        });
+    }
+
+    fn end_function(&mut self) -> (Function, Vec<Upvalue>) {
+        let mut state = self.states.pop().expect("no function to end");
+        state.function.upvalue_count = state.upvalues.len();
+        (state.function, state.upvalues)
+    }
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.states.last_mut().expect("no function being compiled")
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state().scope_depth -= 1;
+
+        loop {
+            let state = self.state_ref();
+            let Some(local) = state.locals.last() else {
+                break;
+            };
+            if local.depth <= state.scope_depth {
+                break;
+            }
+
+            let op = if local.is_captured {
+                OpCode::CloseUpvalue
+            } else {
+                OpCode::Pop
+            };
+            self.emit_op(op);
+            self.state().locals.pop();
+        }
+    }
+
+    /// Forgets the innermost scope without popping its locals, for code that
+    /// leaves the function or raises an error right after.
+    fn discard_scope(&mut self) {
+        let state = self.state();
+        state.scope_depth -= 1;
+        let depth = state.scope_depth;
+        state.locals.retain(|local| local.depth <= depth);
+    }
+
+    fn state_ref(&self) -> &FunctionState {
+        self.states.last().expect("no function being compiled")
+    }
+
+    fn add_local(&mut self, name: &str) {
+        if self.state_ref().locals.len() > u16::MAX as usize {
+            self.error("Too many local variables in function.");
+            return;
+        }
+
+        let depth = self.state_ref().scope_depth;
+        self.state().locals.push(Local {
+            name: name.to_string(),
+            depth,
+            is_captured: false,
+        });
+    }
+
+    /// Binds the value on top of the stack to `name`, as a global at the top
+    /// level or as a new local otherwise.
+    fn define_variable(&mut self, name: &Token) {
+        if self.state_ref().scope_depth > 0 {
+            self.add_local(&name.lexeme);
+            return;
+        }
+
+        let index = self.identifier_constant(&name.lexeme);
+        self.emit_op(OpCode::DefineGlobal);
+        self.emit_u16(index);
+    }
+
+    fn resolve_local(&self, state: usize, name: &str) -> Option<u16> {
+        self.states[state]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u16)
+    }
+
+    fn resolve_upvalue(&mut self, state: usize, name: &str) -> Option<u16> {
+        if state == 0 {
+            return None;
+        }
+
+        if let Some(slot) = self.resolve_local(state - 1, name) {
+            self.states[state - 1].locals[slot as usize].is_captured = true;
+            return Some(self.add_upvalue(state, slot, true));
+        }
+
+        let index = self.resolve_upvalue(state - 1, name)?;
+        Some(self.add_upvalue(state, index, false))
+    }
+
+    fn add_upvalue(&mut self, state: usize, index: u16, is_local: bool) -> u16 {
+        let upvalue = Upvalue { index, is_local };
+        let upvalues = &mut self.states[state].upvalues;
+        if let Some(existing) = upvalues.iter().position(|u| *u == upvalue) {
+            return existing as u16;
+        }
+
+        upvalues.push(upvalue);
+        (upvalues.len() - 1) as u16
+    }
+
+    /// Emits a read of `name`, or a write of the value on top of the stack.
+    fn named_variable(&mut self, name: &str, assign: bool) {
+        let state = self.states.len() - 1;
+        let (op, index) = if let Some(slot) = self.resolve_local(state, name) {
+            let op = if assign {
+                OpCode::SetLocal
+            } else {
+                OpCode::GetLocal
+            };
+            (op, slot)
+        } else if let Some(index) = self.resolve_upvalue(state, name) {
+            let op = if assign {
+                OpCode::SetUpvalue
+            } else {
+                OpCode::GetUpvalue
+            };
+            (op, index)
+        } else {
+            let op = if assign {
+                OpCode::SetGlobal
+            } else {
+                OpCode::GetGlobal
+            };
+            (op, self.identifier_constant(name))
+        };
+
+        self.emit_op(op);
+        self.emit_u16(index);
+    }
+
+    fn identifier_constant(&mut self, name: &str) -> u16 {
+        if let Some(index) = self.state_ref().names.get(name) {
+            return *index;
+        }
+
+        let index = self.make_constant(Constant::String(Rc::from(name)));
+        self.state().names.insert(name.to_string(), index);
+        index
+    }
+
+    fn make_constant(&mut self, constant: Constant) -> u16 {
+        let index = self.state().function.chunk.add_constant(constant);
+        if index > u16::MAX as usize {
+            self.error("Too many constants in one function.");
+            return 0;
+        }
+        index as u16
+    }
+
+    /// Attributes the code emitted from here on to `token`.
+    fn set_location(&mut self, token: &Token) {
+        self.line = token.line;
+        self.span = token.span;
+    }
+
+    fn emit_byte(&mut self, byte: u8) {
+        let (line, span) = (self.line, self.span);
+        self.state().function.chunk.write(byte, line, span);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_u16(&mut self, value: u16) {
+        for byte in value.to_be_bytes() {
+            self.emit_byte(byte);
+        }
+    }
+
+    fn emit_return(&mut self) {
+        if self.state_ref().function_type == FunctionType::Initializer {
+            self.emit_op(OpCode::GetLocal);
+            self.emit_u16(0);
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+        self.emit_op(OpCode::Return);
+    }
+
+    /// Emits a forward jump and returns where its distance goes.
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_u16(u16::MAX);
+        self.state_ref().function.chunk.code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        let chunk = &mut self.state().function.chunk;
+        let distance = chunk.code.len() - offset - 2;
+        let Ok(distance) = u16::try_from(distance) else {
+            self.error("Too much code to jump over.");
+            return;
+        };
+
+        chunk.code[offset..offset + 2].copy_from_slice(&distance.to_be_bytes());
+    }
+
+    fn emit_loop(&mut self, start: usize) {
+        self.emit_op(OpCode::Loop);
+        let distance = self.state_ref().function.chunk.code.len() - start + 2;
+        match u16::try_from(distance) {
+            Ok(distance) => self.emit_u16(distance),
+            Err(_) => self.error("Loop body too large."),
+        }
+    }
+
+    fn error(&mut self, message: &str) {
+        let diagnostic =
+            Diagnostic::error(message, self.span, self.line).with_code(ErrorCode::CompilerLimit);
+        self.lox.report(diagnostic);
+    }
+}
+
+impl<'a> Visitor<()> for Compiler<'a> {
+    fn visit_assign_expr(&mut self, expr: &Expr) {
+        let Expr::Assign(_, name, value) = expr else {
+            unreachable!()
+        };
+
+        self.expression(value);
+        self.set_location(name);
+        self.named_variable(&name.lexeme, true);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Expr) {
+        let Expr::Binary(left, operator, right) = expr else {
+            unreachable!()
+        };
+
+        self.expression(left);
+        self.expression(right);
+        self.set_location(operator);
+
+        let op = match operator.token_type {
+            TokenType::BangEqual => {
+                self.emit_op(OpCode::Equal);
+                OpCode::Not
+            }
+            TokenType::EqualEqual => OpCode::Equal,
+            TokenType::Greater => OpCode::Greater,
+            TokenType::GreaterEqual => OpCode::GreaterEqual,
+            TokenType::Less => OpCode::Less,
+            TokenType::LessEqual => OpCode::LessEqual,
+            TokenType::Plus => OpCode::Add,
+            TokenType::Minus => OpCode::Subtract,
+            TokenType::Star => OpCode::Multiply,
+            TokenType::Slash => OpCode::Divide,
+            _ => unreachable!(),
+        };
+        self.emit_op(op);
+    }
+
+    fn visit_call_expr(&mut self, expr: &Expr) {
+        let Expr::Call(callee, paren, arguments) = expr else {
+            unreachable!()
+        };
+
+        self.expression(callee);
+        for argument in arguments {
+            self.expression(argument);
+        }
+
+        self.set_location(paren);
+        self.emit_op(OpCode::Call);
+        self.emit_byte(arguments.len() as u8);
+    }
+
+    fn visit_get_expr(&mut self, expr: &Expr) {
+        let Expr::Get(object, name) = expr else {
+            unreachable!()
+        };
+
+        self.expression(object);
+        self.set_location(name);
+        let index = self.identifier_constant(&name.lexeme);
+        self.emit_op(OpCode::GetProperty);
+        self.emit_u16(index);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Expr) {
+        let Expr::Grouping(expression) = expr else {
+            unreachable!()
+        };
+
+        self.expression(expression);
+    }
+
+    fn visit_interpolation_expr(&mut self, expr: &Expr) {
+        let Expr::Interpolation(parts) = expr else {
+            unreachable!()
+        };
+
+        for part in parts {
+            self.expression(part);
+        }
+        self.emit_op(OpCode::Interpolate);
+        match u16::try_from(parts.len()) {
+            Ok(count) => self.emit_u16(count),
+            Err(_) => self.error("Too many parts in one interpolated string."),
+        }
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Expr) {
+        let Expr::Literal(value) = expr else {
+            unreachable!()
+        };
+
+        let constant = match value {
+            LiteralValue::Number(n) => Constant::Number(*n),
+            LiteralValue::String(s) => Constant::String(Rc::from(s.as_str())),
+            LiteralValue::True => return self.emit_op(OpCode::True),
+            LiteralValue::False => return self.emit_op(OpCode::False),
+            LiteralValue::Nil => return self.emit_op(OpCode::Nil),
+        };
+
+        let index = self.make_constant(constant);
+        self.emit_op(OpCode::Constant);
+        self.emit_u16(index);
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Expr) {
+        let Expr::Logical(left, operator, right) = expr else {
+            unreachable!()
+        };
+
+        self.expression(left);
+        self.set_location(operator);
+
+        if operator.token_type == TokenType::Or {
+            let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+            let end_jump = self.emit_jump(OpCode::Jump);
+            self.patch_jump(else_jump);
+            self.emit_op(OpCode::Pop);
+            self.expression(right);
+            self.patch_jump(end_jump);
+        } else {
+            let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+            self.emit_op(OpCode::Pop);
+            self.expression(right);
+            self.patch_jump(end_jump);
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &Expr) {
+        let Expr::Set(object, name, value) = expr else {
+            unreachable!()
+        };
+
+        self.expression(object);
+        self.set_location(name);
+        self.emit_op(OpCode::CheckInstance);
+        self.expression(value);
+
+        self.set_location(name);
+        let index = self.identifier_constant(&name.lexeme);
+        self.emit_op(OpCode::SetProperty);
+        self.emit_u16(index);
+    }
+
+    fn visit_super_expr(&mut self, expr: &Expr) {
+        let Expr::Super(_, keyword, method) = expr else {
+            unreachable!()
+        };
+
+        self.set_location(keyword);
+        self.named_variable("this", false);
+        self.named_variable("super", false);
+
+        self.set_location(method);
+        let index = self.identifier_constant(&method.lexeme);
+        self.emit_op(OpCode::GetSuper);
+        self.emit_u16(index);
+    }
+
+    fn visit_this_expr(&mut self, expr: &Expr) {
+        let Expr::This(_, keyword) = expr else {
+            unreachable!()
+        };
+
+        self.set_location(keyword);
+        self.named_variable("this", false);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Expr) {
+        let Expr::Unary(operator, right) = expr else {
+            unreachable!()
+        };
+
+        self.expression(right);
+        self.set_location(operator);
+        match operator.token_type {
+            TokenType::Minus => self.emit_op(OpCode::Negate),
+            TokenType::Bang => self.emit_op(OpCode::Not),
+            _ => unreachable!(),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Expr) {
+        let Expr::Variable(_, name) = expr else {
+            unreachable!()
+        };
+
+        self.set_location(name);
+        self.named_variable(&name.lexeme, false);
+    }
+}
+
+impl<'a> StmtVisitor<()> for Compiler<'a> {
+    fn visit_block_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Block(statements) = stmt else {
+            unreachable!()
+        };
+
+        self.block(statements);
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Class(name, superclass, methods) = stmt else {
+            unreachable!()
+        };
+
+        // The superclass is checked before the class name is bound, as in
+        // the tree-walker.
+        if let Some(superclass) = superclass {
+            self.expression(superclass);
+            self.emit_op(OpCode::CheckSuperclass);
+        }
+
+        self.set_location(name);
+        self.emit_op(OpCode::Nil);
+        self.define_variable(name);
+
+        // Methods capture the superclass from a scope of its own.
+        if let Some(superclass) = superclass {
+            self.begin_scope();
+            self.expression(superclass);
+            self.add_local("super");
+        }
+
+        for method in methods {
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.function(method, function_type);
+        }
+
+        self.set_location(name);
+        let index = self.identifier_constant(&name.lexeme);
+        self.emit_op(OpCode::Class);
+        self.emit_u16(index);
+        match u16::try_from(methods.len()) {
+            Ok(count) => self.emit_u16(count),
+            Err(_) => self.error("Too many methods in one class."),
+        }
+        self.emit_byte(superclass.is_some() as u8);
+
+        self.named_variable(&name.lexeme, true);
+        self.emit_op(OpCode::Pop);
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Expression(expression) = stmt else {
+            unreachable!()
+        };
+
+        self.expression(expression);
+        self.emit_op(OpCode::Pop);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Function(declaration) = stmt else {
+            unreachable!()
+        };
+
+        // A local function is in scope in its own body, so it can recurse.
+        if self.state_ref().scope_depth > 0 {
+            self.add_local(&declaration.name.lexeme);
+            self.function(declaration, FunctionType::Function);
+        } else {
+            self.function(declaration, FunctionType::Function);
+            self.define_variable(&declaration.name);
+        }
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::If(_, condition, then_branch, else_branch) = stmt else {
+            unreachable!()
+        };
+
+        self.expression(condition);
+        let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        then_branch.accept(self);
+
+        let else_jump = self.emit_jump(OpCode::Jump);
+        self.patch_jump(then_jump);
+        self.emit_op(OpCode::Pop);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+        self.patch_jump(else_jump);
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Print(keyword, expression) = stmt else {
+            unreachable!()
+        };
+
+        self.expression(expression);
+        self.set_location(keyword);
+        self.emit_op(OpCode::Print);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Return(keyword, value) = stmt else {
+            unreachable!()
+        };
+
+        self.set_location(keyword);
+        match value {
+            Some(value) => self.expression(value),
+            None if self.state_ref().function_type == FunctionType::Initializer => {
+                self.emit_op(OpCode::GetLocal);
+                self.emit_u16(0);
+            }
+            None => self.emit_op(OpCode::Nil),
+        }
+
+        // Leaving a `try` uninstalls its handlers and runs its `finally`
+        // block, innermost first, with the return value kept underneath.
+        let tries = self.state_ref().tries.clone();
+        if !tries.is_empty() {
+            self.begin_scope();
+            self.add_local("");
+            while let Some(region) = self.state().tries.pop() {
+                for _ in 0..region.handlers {
+                    self.emit_op(OpCode::PopHandler);
+                }
+                if let Some(finally) = &region.finally {
+                    self.block(finally);
+                }
+            }
+            self.discard_scope();
+            self.state().tries = tries;
+        }
+
+        self.set_location(keyword);
+        self.emit_op(OpCode::Return);
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Throw(keyword, value) = stmt else {
+            unreachable!()
+        };
+
+        self.expression(value);
+        self.set_location(keyword);
+        self.emit_op(OpCode::Throw);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Try(body, catch, finally) = stmt else {
+            unreachable!()
+        };
+
+        // The `finally` handler covers both the body and the `catch` block.
+        let finally_handler = finally
+            .as_ref()
+            .map(|_| self.emit_jump(OpCode::PushFinally));
+        let catch_handler = catch.as_ref().map(|_| self.emit_jump(OpCode::PushCatch));
+        self.state().tries.push(TryRegion {
+            handlers: finally_handler.iter().count() + catch_handler.iter().count(),
+            finally: finally.clone(),
+        });
+
+        self.block(body);
+
+        if let (Some(handler), Some((name, body))) = (catch_handler, catch) {
+            self.emit_op(OpCode::PopHandler);
+            self.state().tries.last_mut().unwrap().handlers -= 1;
+            let skip = self.emit_jump(OpCode::Jump);
+
+            // The handler leaves the error value where the variable lives.
+            self.patch_jump(handler);
+            self.begin_scope();
+            self.add_local(&name.lexeme);
+            self.statements(body);
+            self.end_scope();
+            self.patch_jump(skip);
+        }
+
+        self.state().tries.pop();
+
+        if let (Some(handler), Some(finally)) = (finally_handler, finally) {
+            self.emit_op(OpCode::PopHandler);
+            self.block(finally);
+            let skip = self.emit_jump(OpCode::Jump);
+
+            // On an error, the handler leaves a placeholder for the error it
+            // set aside, then the block runs and raises the error again.
+            self.patch_jump(handler);
+            self.begin_scope();
+            self.add_local("");
+            self.block(finally);
+            self.emit_op(OpCode::Rethrow);
+            self.discard_scope();
+            self.patch_jump(skip);
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Var(name, initializer) = stmt else {
+            unreachable!()
+        };
+
+        match initializer {
+            Some(initializer) => self.expression(initializer),
+            None => self.emit_op(OpCode::Nil),
+        }
+        self.set_location(name);
+        self.define_variable(name);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::While(_, condition, body) = stmt else {
+            unreachable!()
+        };
+
+        let loop_start = self.state_ref().function.chunk.code.len();
+        self.expression(condition);
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+        body.accept(self);
+        self.emit_loop(loop_start);
+
+        self.patch_jump(exit_jump);
+        self.emit_op(OpCode::Pop);
+    }
+}
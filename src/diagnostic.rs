@@ -0,0 +1,373 @@
+use crate::error_code::ErrorCode;
+use crate::token::Span;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A secondary span pointing at related source, such as a previous declaration.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<ErrorCode>,
+    pub message: String,
+    pub span: Span,
+    pub line: usize,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: &str, span: Span, line: usize) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message, span, line)
+    }
+
+    pub fn warning(message: &str, span: Span, line: usize) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message, span, line)
+    }
+
+    fn new(severity: Severity, message: &str, span: Span, line: usize) -> Diagnostic {
+        Diagnostic {
+            severity,
+            code: None,
+            message: message.to_string(),
+            span,
+            line,
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> Diagnostic {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: &str) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(note.to_string());
+        self
+    }
+
+    /// Renders the diagnostic with the offending source line and a caret
+    /// underline, falling back to the plain form if the span is unknown.
+    pub fn render(&self, sources: &SourceMap) -> String {
+        let Some(primary) = sources.snippet(self.span) else {
+            return self.to_string();
+        };
+
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .filter_map(|label| Some((sources.snippet(label.span)?, &label.message)))
+            .collect();
+        let widest_line = labels
+            .iter()
+            .map(|(snippet, _)| snippet.line)
+            .fold(primary.line, usize::max);
+
+        let mut out = format!("{}: {}\n", self.heading(), self.message);
+        let gutter = " ".repeat(widest_line.to_string().len());
+        out.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            gutter, primary.name, primary.line, primary.column
+        ));
+        out.push_str(&format!("{} |\n", gutter));
+        primary.write_underlined(&mut out, &gutter, '^', "");
+
+        for (snippet, message) in labels {
+            snippet.write_underlined(&mut out, &gutter, '-', message);
+        }
+
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+
+        out
+    }
+
+    /// Serializes the diagnostic as a single-line JSON object for tools.
+    pub fn to_json(&self, sources: &SourceMap) -> String {
+        let location = |span: Span| match sources.snippet(span) {
+            Some(snippet) => format!(
+                "\"file\":{},\"line\":{},\"column\":{},\"span\":{{\"start\":{},\"end\":{}}}",
+                json_string(snippet.name),
+                snippet.line,
+                snippet.column,
+                snippet.start,
+                snippet.end
+            ),
+            None => format!(
+                "\"file\":null,\"line\":{},\"column\":null,\"span\":null",
+                self.line
+            ),
+        };
+
+        let labels: Vec<String> = self
+            .labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{{},\"message\":{}}}",
+                    location(label.span),
+                    json_string(&label.message)
+                )
+            })
+            .collect();
+        let notes: Vec<String> = self.notes.iter().map(|note| json_string(note)).collect();
+
+        let code = match self.code {
+            Some(code) => json_string(code.as_str()),
+            None => String::from("null"),
+        };
+
+        format!(
+            "{{{},\"severity\":\"{}\",\"code\":{},\"message\":{},\"labels\":[{}],\"notes\":[{}]}}",
+            location(self.span),
+            self.severity,
+            code,
+            json_string(&self.message),
+            labels.join(","),
+            notes.join(",")
+        )
+    }
+
+    /// The severity followed by the code, if any, as in `error[E0100]`.
+    fn heading(&self) -> String {
+        match self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[line {}] {}: {}",
+            self.line,
+            self.heading(),
+            self.message
+        )
+    }
+}
+
+struct SourceFile {
+    name: String,
+    text: String,
+    offset: usize,
+}
+
+/// Every source the interpreter has seen, laid out in one byte-offset space so
+/// that a span from any earlier `eval` can still be rendered.
+#[derive(Default)]
+pub struct SourceMap {
+    /// Sorted by offset, since offsets only ever grow.
+    files: Vec<SourceFile>,
+    next_offset: usize,
+}
+
+/// A resolved view of where a span falls in its source file.
+pub struct Snippet<'a> {
+    pub name: &'a str,
+    pub line: usize,
+    pub column: usize,
+    /// Byte offsets of the span within its own file.
+    pub start: usize,
+    pub end: usize,
+    text: &'a str,
+    width: usize,
+}
+
+impl SourceMap {
+    /// Registers `text` and returns the offset its spans start at.
+    pub fn add(&mut self, name: &str, text: &str) -> usize {
+        // Leave a one-byte gap so the end-of-file position of one source is
+        // never mistaken for the start of the next.
+        let offset = self.next_offset;
+        self.next_offset = offset + text.len() + 1;
+
+        self.files.push(SourceFile {
+            name: name.to_string(),
+            text: text.to_string(),
+            offset,
+        });
+        offset
+    }
+
+    /// Forgets the text of every source added so far. Offsets are never
+    /// reused, so spans into a forgotten source simply have no snippet.
+    pub fn clear(&mut self) {
+        self.files.clear();
+    }
+
+    pub fn snippet(&self, span: Span) -> Option<Snippet<'_>> {
+        let owners = self.files.partition_point(|file| file.offset <= span.start);
+        let file = self.files[..owners].last()?;
+        let start = span.start - file.offset;
+        if start > file.text.len() || !file.text.is_char_boundary(start) {
+            return None;
+        }
+
+        let line_start = file.text[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = file.text[start..]
+            .find('\n')
+            .map_or(file.text.len(), |i| start + i);
+        let end = (span.end - file.offset).clamp(start, line_end);
+
+        Some(Snippet {
+            name: &file.name,
+            line: file.text[..start].matches('\n').count() + 1,
+            column: file.text[line_start..start].chars().count() + 1,
+            start,
+            end: span.end - file.offset,
+            text: file.text[line_start..line_end].trim_end_matches('\r'),
+            width: file.text[start..end].chars().count().max(1),
+        })
+    }
+}
+
+impl Snippet<'_> {
+    fn write_underlined(&self, out: &mut String, gutter: &str, marker: char, message: &str) {
+        let padding = " ".repeat(gutter.len() - self.line.to_string().len());
+        out.push_str(&format!("{}{} | {}\n", self.line, padding, self.text));
+
+        let underline = marker.to_string().repeat(self.width);
+        // Tabs are copied so the marker lines up however wide they are shown.
+        let indent: String = self
+            .text
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let line = format!("{} | {}{} {}", gutter, indent, underline, message);
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_underlines_the_span() {
+        let mut sources = SourceMap::default();
+        sources.add("first.lox", "print 1;");
+        let offset = sources.add("second.lox", "var a = 1;\nprint a +;\n");
+
+        let diagnostic = Diagnostic::error(
+            "Expected expression.",
+            Span::new(offset + 20, offset + 21),
+            2,
+        )
+        .with_code(ErrorCode::ExpectedExpression)
+        .with_note("an operand is missing");
+
+        assert_eq!(
+            diagnostic.render(&sources),
+            "error[E0100]: Expected expression.\n \
+             --> second.lox:2:10\n  \
+             |\n\
+             2 | print a +;\n  \
+             |          ^\n  \
+             = note: an operand is missing\n"
+        );
+    }
+
+    #[test]
+    fn test_underline_keeps_the_tabs_of_the_line() {
+        let mut sources = SourceMap::default();
+        sources.add("tabs.lox", "fun f() {\n\t\treturn -\"x\";\n}");
+
+        let diagnostic = Diagnostic::error("Operand must be a number.", Span::new(19, 20), 2);
+        assert!(
+            diagnostic
+                .render(&sources)
+                .contains("2 | \t\treturn -\"x\";\n  | \t\t       ^\n")
+        );
+    }
+
+    #[test]
+    fn test_cleared_sources_are_not_mistaken_for_new_ones() {
+        let mut sources = SourceMap::default();
+        sources.add("first.lox", "print 1;");
+        let offset = sources.add("second.lox", "print 2;");
+        assert_eq!(
+            sources.snippet(Span::new(offset, offset + 5)).unwrap().name,
+            "second.lox"
+        );
+
+        sources.clear();
+        let next = sources.add("third.lox", "print 3;");
+        assert!(next > offset);
+        assert!(sources.snippet(Span::new(offset, offset + 5)).is_none());
+        assert_eq!(
+            sources.snippet(Span::new(next, next + 5)).unwrap().name,
+            "third.lox"
+        );
+    }
+
+    #[test]
+    fn test_json_uses_file_relative_positions() {
+        let mut sources = SourceMap::default();
+        sources.add("first.lox", "print 1;");
+        let offset = sources.add("say \"hi\".lox", "print 1 < nil;");
+
+        let diagnostic = Diagnostic::error(
+            "Operands must be \"numbers\".",
+            Span::new(offset + 8, offset + 9),
+            1,
+        )
+        .with_code(ErrorCode::OperandType);
+
+        assert_eq!(
+            diagnostic.to_json(&sources),
+            r#"{"file":"say \"hi\".lox","line":1,"column":9,"span":{"start":8,"end":9},"severity":"error","code":"E0300","message":"Operands must be \"numbers\".","labels":[],"notes":[]}"#
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::chunk::{Chunk, Constant, Function, OpCode};
+use std::fmt::Write;
+
+/// Lists the code of `function` followed by that of every function it
+/// defines, one instruction per line.
+pub fn disassemble(function: &Function) -> String {
+    let mut listing = String::new();
+    disassemble_function(function, &mut listing);
+    listing
+}
+
+fn disassemble_function(function: &Function, listing: &mut String) {
+    let _ = writeln!(listing, "== {} ==", function.name);
+    let chunk = &function.chunk;
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        offset = disassemble_instruction(chunk, offset, listing);
+    }
+
+    for constant in &chunk.constants {
+        if let Constant::Function(function) = constant {
+            listing.push('\n');
+            disassemble_function(function, listing);
+        }
+    }
+}
+
+/// Writes the instruction at `offset` as a line of `listing` and returns the
+/// offset of the next one.
+pub fn disassemble_instruction(chunk: &Chunk, offset: usize, listing: &mut String) -> usize {
+    let _ = write!(listing, "{:04} ", offset);
+    if offset > 0 && chunk.lines[offset] == chunk.lines[offset - 1] {
+        listing.push_str("   | ");
+    } else {
+        let _ = write!(listing, "{:4} ", chunk.lines[offset]);
+    }
+
+    let Some(op) = OpCode::from_byte(chunk.code[offset]) else {
+        let _ = writeln!(listing, "Unknown opcode {}", chunk.code[offset]);
+        return offset + 1;
+    };
+    let name = format!("{:?}", op);
+
+    match op {
+        OpCode::Constant
+        | OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper => {
+            let index = chunk.read_u16(offset + 1);
+            let _ = writeln!(
+                listing,
+                "{:<16} {:4} '{}'",
+                name,
+                index,
+                constant(chunk, index)
+            );
+            offset + 3
+        }
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::Interpolate => {
+            let _ = writeln!(listing, "{:<16} {:4}", name, chunk.read_u16(offset + 1));
+            offset + 3
+        }
+        OpCode::Call => {
+            let _ = writeln!(listing, "{:<16} {:4}", name, chunk.code[offset + 1]);
+            offset + 2
+        }
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::PushCatch | OpCode::PushFinally => {
+            let target = offset + 3 + chunk.read_u16(offset + 1) as usize;
+            let _ = writeln!(listing, "{:<16} {:4} -> {}", name, offset, target);
+            offset + 3
+        }
+        OpCode::Loop => {
+            let target = offset + 3 - chunk.read_u16(offset + 1) as usize;
+            let _ = writeln!(listing, "{:<16} {:4} -> {}", name, offset, target);
+            offset + 3
+        }
+        OpCode::Closure => {
+            let index = chunk.read_u16(offset + 1);
+            let _ = writeln!(
+                listing,
+                "{:<16} {:4} {}",
+                name,
+                index,
+                constant(chunk, index)
+            );
+
+            let mut offset = offset + 3;
+            if let Constant::Function(function) = &chunk.constants[index as usize] {
+                for _ in 0..function.upvalue_count {
+                    let kind = if chunk.code[offset] == 1 {
+                        "local"
+                    } else {
+                        "upvalue"
+                    };
+                    let _ = writeln!(
+                        listing,
+                        "{:04}    |                     {} {}",
+                        offset,
+                        kind,
+                        chunk.read_u16(offset + 1)
+                    );
+                    offset += 3;
+                }
+            }
+            offset
+        }
+        OpCode::Class => {
+            let index = chunk.read_u16(offset + 1);
+            let count = chunk.read_u16(offset + 3);
+            let superclass = if chunk.code[offset + 5] == 1 {
+                " with superclass"
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                listing,
+                "{:<16} {:4} '{}' {} methods{}",
+                name,
+                index,
+                constant(chunk, index),
+                count,
+                superclass
+            );
+            offset + 6
+        }
+        OpCode::Nil
+        | OpCode::True
+        | OpCode::False
+        | OpCode::Pop
+        | OpCode::CheckInstance
+        | OpCode::Equal
+        | OpCode::Greater
+        | OpCode::GreaterEqual
+        | OpCode::Less
+        | OpCode::LessEqual
+        | OpCode::Add
+        | OpCode::Subtract
+        | OpCode::Multiply
+        | OpCode::Divide
+        | OpCode::Not
+        | OpCode::Negate
+        | OpCode::Print
+        | OpCode::CloseUpvalue
+        | OpCode::Return
+        | OpCode::CheckSuperclass
+        | OpCode::Throw
+        | OpCode::PopHandler
+        | OpCode::Rethrow => {
+            let _ = writeln!(listing, "{}", name);
+            offset + 1
+        }
+    }
+}
+
+fn constant(chunk: &Chunk, index: u16) -> String {
+    match &chunk.constants[index as usize] {
+        Constant::Number(n) => n.to_string(),
+        Constant::String(s) => s.to_string(),
+        Constant::Function(function) => format!("<fn {}>", function.name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Lox;
+
+    #[test]
+    fn test_lists_offsets_lines_operands_and_nested_functions() {
+        let mut lox = Lox::new();
+        let listing = lox
+            .disassemble("var a = 1;\nfun f(x) {\n  return x + a;\n}\nprint f(2);")
+            .unwrap();
+
+        let expected = "\
+== script ==
+0000    1 Constant            0 '1'
+0003    | DefineGlobal        1 'a'
+0006    2 Closure             2 <fn f>
+0009    | DefineGlobal        3 'f'
+0012    5 GetGlobal           3 'f'
+0015    | Constant            4 '2'
+0018    | Call                1
+0020    | Print
+0021    | Nil
+0022    | Return
+
+== f ==
+0000    3 GetLocal            1
+0003    | GetGlobal           0 'a'
+0006    | Add
+0007    | Return
+0008    | Nil
+0009    | Return
+";
+        assert_eq!(listing, expected);
+    }
+}
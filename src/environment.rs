@@ -0,0 +1,112 @@
+use crate::error_code::ErrorCode;
+use crate::interpreter::LoxValue;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<String, LoxValue>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        Environment::default()
+    }
+
+    pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Environment {
+        Environment {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    pub fn define(&mut self, name: &str, value: LoxValue) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<LoxValue, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(RuntimeError::new(
+                name.clone(),
+                ErrorCode::UndefinedVariable,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    pub fn get_at(&self, distance: usize, name: &str) -> Option<LoxValue> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+
+        self.enclosing.as_ref()?.borrow().get_at(distance - 1, name)
+    }
+
+    pub fn assign_at(&mut self, distance: usize, name: &Token, value: LoxValue) {
+        if distance == 0 {
+            self.values.insert(name.lexeme.clone(), value);
+            return;
+        }
+
+        if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow_mut().assign_at(distance - 1, name, value);
+        }
+    }
+
+    pub fn assign(&mut self, name: &Token, value: LoxValue) -> Result<(), RuntimeError> {
+        if let Some(slot) = self.values.get_mut(&name.lexeme) {
+            *slot = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(RuntimeError::new(
+                name.clone(),
+                ErrorCode::UndefinedVariable,
+                &format!("Undefined variable '{}'.", name.lexeme),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{Literal, Span, TokenType};
+
+    fn identifier(name: &str) -> Token {
+        Token::new(
+            TokenType::Literal,
+            name.into(),
+            Some(Literal::Identifier(name.into())),
+            1,
+            1,
+            Span::new(0, name.len()),
+        )
+    }
+
+    #[test]
+    fn test_enclosed_environment_shadows_and_assigns_outward() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals.borrow_mut().define("a", LoxValue::Number(1.0));
+
+        let mut inner = Environment::new_enclosed(globals.clone());
+        inner.define("b", LoxValue::Number(2.0));
+        inner
+            .assign(&identifier("a"), LoxValue::Number(3.0))
+            .unwrap();
+
+        assert!(globals.borrow().get(&identifier("a")).unwrap() == LoxValue::Number(3.0));
+        assert!(globals.borrow().get(&identifier("b")).is_err());
+    }
+}
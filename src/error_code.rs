@@ -0,0 +1,610 @@
+use std::fmt;
+
+/// A stable identifier for a family of diagnostics. Messages may be reworded
+/// freely, but a code keeps its meaning once it has been published.
+///
+/// Codes are grouped by the phase that reports them: `E00xx` for the
+/// scanner, `E01xx` for the parser, `E02xx` for the resolver, `E03xx` for
+/// runtime errors, `E04xx` for the bytecode compiler and `E05xx` for the
+/// warnings of the optimizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidEscape,
+    UnterminatedComment,
+    ExpectedExpression,
+    ExpectedToken,
+    ExpectedName,
+    InvalidAssignmentTarget,
+    TooManyArguments,
+    DuplicateDeclaration,
+    ReadInOwnInitializer,
+    TopLevelReturn,
+    ReturnValueFromInitializer,
+    ThisOutsideClass,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    InheritFromSelf,
+    OperandType,
+    UndefinedVariable,
+    UndefinedProperty,
+    NotCallable,
+    ArityMismatch,
+    NotAnInstance,
+    SuperclassNotClass,
+    NativeError,
+    UncaughtThrow,
+    StackOverflow,
+    OutputFailed,
+    CompilerLimit,
+    UnreachableCode,
+}
+
+impl ErrorCode {
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::UnexpectedCharacter,
+        ErrorCode::UnterminatedString,
+        ErrorCode::InvalidEscape,
+        ErrorCode::UnterminatedComment,
+        ErrorCode::ExpectedExpression,
+        ErrorCode::ExpectedToken,
+        ErrorCode::ExpectedName,
+        ErrorCode::InvalidAssignmentTarget,
+        ErrorCode::TooManyArguments,
+        ErrorCode::DuplicateDeclaration,
+        ErrorCode::ReadInOwnInitializer,
+        ErrorCode::TopLevelReturn,
+        ErrorCode::ReturnValueFromInitializer,
+        ErrorCode::ThisOutsideClass,
+        ErrorCode::SuperOutsideClass,
+        ErrorCode::SuperWithoutSuperclass,
+        ErrorCode::InheritFromSelf,
+        ErrorCode::OperandType,
+        ErrorCode::UndefinedVariable,
+        ErrorCode::UndefinedProperty,
+        ErrorCode::NotCallable,
+        ErrorCode::ArityMismatch,
+        ErrorCode::NotAnInstance,
+        ErrorCode::SuperclassNotClass,
+        ErrorCode::NativeError,
+        ErrorCode::UncaughtThrow,
+        ErrorCode::StackOverflow,
+        ErrorCode::OutputFailed,
+        ErrorCode::CompilerLimit,
+        ErrorCode::UnreachableCode,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0001",
+            ErrorCode::UnterminatedString => "E0002",
+            ErrorCode::InvalidEscape => "E0003",
+            ErrorCode::UnterminatedComment => "E0004",
+            ErrorCode::ExpectedExpression => "E0100",
+            ErrorCode::ExpectedToken => "E0101",
+            ErrorCode::ExpectedName => "E0102",
+            ErrorCode::InvalidAssignmentTarget => "E0103",
+            ErrorCode::TooManyArguments => "E0104",
+            ErrorCode::DuplicateDeclaration => "E0200",
+            ErrorCode::ReadInOwnInitializer => "E0201",
+            ErrorCode::TopLevelReturn => "E0202",
+            ErrorCode::ReturnValueFromInitializer => "E0203",
+            ErrorCode::ThisOutsideClass => "E0204",
+            ErrorCode::SuperOutsideClass => "E0205",
+            ErrorCode::SuperWithoutSuperclass => "E0206",
+            ErrorCode::InheritFromSelf => "E0207",
+            ErrorCode::OperandType => "E0300",
+            ErrorCode::UndefinedVariable => "E0301",
+            ErrorCode::UndefinedProperty => "E0302",
+            ErrorCode::NotCallable => "E0303",
+            ErrorCode::ArityMismatch => "E0304",
+            ErrorCode::NotAnInstance => "E0305",
+            ErrorCode::SuperclassNotClass => "E0306",
+            ErrorCode::NativeError => "E0307",
+            ErrorCode::UncaughtThrow => "E0308",
+            ErrorCode::StackOverflow => "E0309",
+            ErrorCode::OutputFailed => "E0310",
+            ErrorCode::CompilerLimit => "E0400",
+            ErrorCode::UnreachableCode => "E0500",
+        }
+    }
+
+    /// Looks a code up by its `E0xxx` name, ignoring case.
+    pub fn from_code(code: &str) -> Option<ErrorCode> {
+        ErrorCode::ALL
+            .iter()
+            .copied()
+            .find(|c| c.as_str().eq_ignore_ascii_case(code))
+    }
+
+    /// A longer description of the error with an example and a fix, as
+    /// printed by `lox --explain`.
+    pub fn explanation(self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => {
+                r#"A character that is not part of Lox's syntax appeared in the source.
+
+Erroneous code example:
+
+    var price = 10 # 2;
+
+Lox has no `#` operator. Remove the character or replace it with one that
+means what you intended:
+
+    var price = 10 / 2;
+"#
+            }
+            ErrorCode::UnterminatedString => {
+                r#"A string literal was opened but never closed.
+
+Erroneous code example:
+
+    print "hello;
+
+Every string starts and ends with a double quote. Add the closing quote:
+
+    print "hello";
+"#
+            }
+            ErrorCode::InvalidEscape => {
+                r#"A backslash in a string literal starts an escape sequence Lox does not
+recognize.
+
+Erroneous code example:
+
+    print "C:\data";
+
+The supported escapes are `\n`, `\t`, `\\`, `\"`, `\$`, `\0` and `\u{XXXX}` with
+one to six hex digits naming a Unicode scalar value. Escape a literal backslash
+with another backslash:
+
+    print "C:\\data";
+"#
+            }
+            ErrorCode::UnterminatedComment => {
+                r#"A block comment was opened with `/*` but never closed.
+
+Erroneous code example:
+
+    /* Compute the total.
+    var total = 1 + 2;
+
+Block comments run until the first `*/` and do not nest. Close the comment:
+
+    /* Compute the total. */
+    var total = 1 + 2;
+"#
+            }
+            ErrorCode::ExpectedExpression => {
+                r#"The parser needed an expression but found something else.
+
+Erroneous code example:
+
+    var total = 1 +;
+
+An operator is missing its operand, or a statement ended early. Supply the
+missing expression:
+
+    var total = 1 + 2;
+"#
+            }
+            ErrorCode::ExpectedToken => {
+                r#"A required piece of punctuation or keyword is missing.
+
+Erroneous code example:
+
+    print "done"
+
+Statements end with `;`, and parentheses and braces must be balanced. Add
+the token named in the message:
+
+    print "done";
+"#
+            }
+            ErrorCode::ExpectedName => {
+                r#"A declaration or property access is missing its name.
+
+Erroneous code example:
+
+    var = 1;
+
+Variables, functions, classes, parameters and properties all need an
+identifier. Give it a name:
+
+    var count = 1;
+"#
+            }
+            ErrorCode::InvalidAssignmentTarget => {
+                r#"The left-hand side of `=` is not something that can be assigned to.
+
+Erroneous code example:
+
+    1 + a = 3;
+
+Only variables and fields of instances can be assigned. Assign to a variable
+or a field instead:
+
+    a = 3 - 1;
+"#
+            }
+            ErrorCode::TooManyArguments => {
+                r#"A function declares or is called with more than 255 parameters.
+
+Erroneous code example:
+
+    fun f(a1, a2, a3, /* ... */ a256) {}
+
+Lox caps parameter and argument lists at 255. Group related values into an
+instance and pass that instead:
+
+    class Args {}
+    fun f(args) {}
+"#
+            }
+            ErrorCode::DuplicateDeclaration => {
+                r#"A name was declared twice in the same local scope.
+
+Erroneous code example:
+
+    {
+      var a = 1;
+      var a = 2;
+    }
+
+Inside a block a name can only be declared once. Assign to the existing
+variable, or pick a different name:
+
+    {
+      var a = 1;
+      a = 2;
+    }
+"#
+            }
+            ErrorCode::ReadInOwnInitializer => {
+                r#"A local variable was used in the expression that initializes it.
+
+Erroneous code example:
+
+    var a = 1;
+    {
+      var a = a + 1;
+    }
+
+The inner `a` is not defined until its initializer finishes. Use a different
+name for the new variable:
+
+    var a = 1;
+    {
+      var b = a + 1;
+    }
+"#
+            }
+            ErrorCode::TopLevelReturn => {
+                r#"A `return` statement appeared outside of any function.
+
+Erroneous code example:
+
+    return 1;
+
+`return` only makes sense inside a function or method body. Move the code
+into a function:
+
+    fun one() {
+      return 1;
+    }
+"#
+            }
+            ErrorCode::ReturnValueFromInitializer => {
+                r#"An `init` method tried to return a value.
+
+Erroneous code example:
+
+    class Point {
+      init() {
+        return 1;
+      }
+    }
+
+Initializers always return the new instance. Use a bare `return;` to leave
+early:
+
+    class Point {
+      init() {
+        return;
+      }
+    }
+"#
+            }
+            ErrorCode::ThisOutsideClass => {
+                r#"`this` was used outside of a method.
+
+Erroneous code example:
+
+    fun name() {
+      return this.name;
+    }
+
+`this` refers to the instance a method was called on, so it only exists
+inside class bodies. Pass the instance as a parameter instead:
+
+    fun name(person) {
+      return person.name;
+    }
+"#
+            }
+            ErrorCode::SuperOutsideClass => {
+                r#"`super` was used outside of a method.
+
+Erroneous code example:
+
+    fun greet() {
+      super.greet();
+    }
+
+`super` refers to the superclass of the enclosing class. Only use it inside
+the methods of a class that has one.
+"#
+            }
+            ErrorCode::SuperWithoutSuperclass => {
+                r#"`super` was used in a class that does not inherit from anything.
+
+Erroneous code example:
+
+    class Cat {
+      speak() {
+        super.speak();
+      }
+    }
+
+Declare the superclass with `<`:
+
+    class Cat < Animal {
+      speak() {
+        super.speak();
+      }
+    }
+"#
+            }
+            ErrorCode::InheritFromSelf => {
+                r#"A class named itself as its own superclass.
+
+Erroneous code example:
+
+    class Node < Node {}
+
+A class can only inherit from a different, already declared class:
+
+    class Node < Base {}
+"#
+            }
+            ErrorCode::OperandType => {
+                r#"An operator was applied to values of the wrong type.
+
+Erroneous code example:
+
+    print "total: " + 3;
+
+Arithmetic and comparison operators need numbers, and `+` needs either two
+numbers or two strings. Convert or check the values first:
+
+    print "total: " + "3";
+"#
+            }
+            ErrorCode::UndefinedVariable => {
+                r#"A variable was read or assigned before it was declared.
+
+Erroneous code example:
+
+    print count;
+
+Declare the variable with `var` before using it, and check its spelling:
+
+    var count = 0;
+    print count;
+"#
+            }
+            ErrorCode::UndefinedProperty => {
+                r#"An instance has no field or method with the given name.
+
+Erroneous code example:
+
+    class Point {}
+    print Point().x;
+
+Set the field before reading it, usually in `init`:
+
+    class Point {
+      init() {
+        this.x = 0;
+      }
+    }
+    print Point().x;
+"#
+            }
+            ErrorCode::NotCallable => {
+                r#"A value that is not a function or class was called.
+
+Erroneous code example:
+
+    var name = "lox";
+    name();
+
+Only functions, methods and classes can be called. Check that the callee
+holds what you expect.
+"#
+            }
+            ErrorCode::ArityMismatch => {
+                r#"A function was called with the wrong number of arguments.
+
+Erroneous code example:
+
+    fun add(a, b) {
+      return a + b;
+    }
+    add(1);
+
+Pass exactly as many arguments as the function declares parameters:
+
+    add(1, 2);
+"#
+            }
+            ErrorCode::NotAnInstance => {
+                r#"A property was read or written on a value that is not an instance.
+
+Erroneous code example:
+
+    var n = 3;
+    print n.size;
+
+Only instances of classes have fields and methods. Use an instance instead:
+
+    class Box {}
+    var b = Box();
+    b.size = 3;
+    print b.size;
+"#
+            }
+            ErrorCode::SuperclassNotClass => {
+                r#"The value after `<` in a class declaration is not a class.
+
+Erroneous code example:
+
+    var Base = "base";
+    class Derived < Base {}
+
+A class can only inherit from another class:
+
+    class Base {}
+    class Derived < Base {}
+"#
+            }
+            ErrorCode::NativeError => {
+                r#"A function provided by the host program reported an error.
+
+Native functions such as `clock` are written in Rust, and the message comes
+from the function itself. For example, an embedder's `sum` may reject
+non-numbers:
+
+    sum(1, nil);
+
+Check the function's documentation for the arguments it accepts:
+
+    sum(1, 2);
+"#
+            }
+            ErrorCode::UncaughtThrow => {
+                r#"A value was thrown with `throw` and no `catch` handled it.
+
+Erroneous code example:
+
+    fun parse(text) {
+      throw "not a number: " + text;
+    }
+    parse("abc");
+
+The message is the thrown value, or its `message` field if it has one. Wrap
+the code that may throw in a `try` block and handle the error:
+
+    try {
+      parse("abc");
+    } catch (e) {
+      print e;
+    }
+"#
+            }
+            ErrorCode::StackOverflow => {
+                r#"Calls were nested more deeply than the interpreter allows.
+
+Erroneous code example:
+
+    fun count(n) {
+      return count(n + 1);
+    }
+    count(0);
+
+This is almost always a recursive function that never reaches its base
+case. Add one, or rewrite deep recursion as a loop:
+
+    fun count(n) {
+      if (n >= 10) return n;
+      return count(n + 1);
+    }
+"#
+            }
+            ErrorCode::OutputFailed => {
+                r#"A `print` statement could not write its output.
+
+The output stream was closed or failed, for example because a script was
+piped into a command that stopped reading early:
+
+    lox script.lox | head -1
+
+When embedding Lox, this is the error returned by the writer passed to
+`set_output`. The script stops at the `print`; a `try` block can catch the
+error like any other.
+"#
+            }
+            ErrorCode::CompilerLimit => {
+                r#"A function is too large for the bytecode backend (`--backend=vm`).
+
+The VM addresses constants, local variables and jump distances with 16-bit
+operands, so a single function can hold at most 65536 constants and locals,
+and a jump can cross at most 65535 bytes of bytecode.
+
+Erroneous code example:
+
+    fun huge() {
+      // ... tens of thousands of statements in one loop body ...
+    }
+
+Split the function into smaller functions, or run the script with the
+tree-walking backend, which has no such limits.
+"#
+            }
+            ErrorCode::UnreachableCode => {
+                r#"A statement can never run, so it was removed before execution.
+
+This is a warning: the rest of the program still runs. It is reported for
+code after a `return` or `throw` in the same block, and for branches and
+loops whose condition is a constant.
+
+Erroneous code example:
+
+    fun sign(n) {
+      return n < 0;
+      print "checked";
+    }
+
+    if (false) print "debugging";
+
+Delete the dead code, or move it before the `return`. Pass `--no-warnings`
+to silence these diagnostics without changing the program.
+"#
+            }
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_are_unique_and_round_trip() {
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_code(code.as_str()), Some(*code));
+        }
+        assert_eq!(
+            ErrorCode::from_code("e0301"),
+            Some(ErrorCode::UndefinedVariable)
+        );
+        assert_eq!(ErrorCode::from_code("E9999"), None);
+    }
+}
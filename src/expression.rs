@@ -1,4 +1,11 @@
-use crate::token::{self, Literal, Token};
+use crate::token::Token;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// How many scopes out from a reference its local variable is declared, as
+/// filled in by the resolver. Globals are left as `None`. Keeping it on the
+/// node means it is freed along with the code that uses it.
+pub type Depth = Cell<Option<usize>>;
 
 #[derive(Debug, Clone)]
 pub enum LiteralValue {
@@ -9,33 +16,119 @@ pub enum LiteralValue {
     Nil,
 }
 
+#[derive(Clone)]
 pub enum Expr {
+    Assign(Depth, Token, Box<Expr>),
     Binary(Box<Expr>, Token, Box<Expr>),
+    Call(Box<Expr>, Token, Vec<Expr>),
+    Get(Box<Expr>, Token),
     Grouping(Box<Expr>),
+    /// The parts of an interpolated string, stringified and concatenated.
+    Interpolation(Vec<Expr>),
     Literal(LiteralValue),
+    Logical(Box<Expr>, Token, Box<Expr>),
+    Set(Box<Expr>, Token, Box<Expr>),
+    Super(Depth, Token, Token),
+    This(Depth, Token),
     Unary(Token, Box<Expr>),
+    Variable(Depth, Token),
+}
+
+pub struct FunctionDecl {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Clone)]
+pub enum Stmt {
+    Block(Vec<Stmt>),
+    Class(Token, Option<Expr>, Vec<Rc<FunctionDecl>>),
+    Expression(Expr),
+    Function(Rc<FunctionDecl>),
+    If(Token, Expr, Box<Stmt>, Option<Box<Stmt>>),
+    Print(Token, Expr),
+    Return(Token, Option<Expr>),
+    Throw(Token, Expr),
+    /// A protected block, an optional `catch` binding the error to a name,
+    /// and an optional `finally` block.
+    Try(Vec<Stmt>, Option<(Token, Vec<Stmt>)>, Option<Vec<Stmt>>),
+    Var(Token, Option<Expr>),
+    While(Token, Expr, Box<Stmt>),
 }
 
 pub trait Visitor<T> {
+    fn visit_assign_expr(&mut self, expr: &Expr) -> T;
     fn visit_binary_expr(&mut self, expr: &Expr) -> T;
+    fn visit_call_expr(&mut self, expr: &Expr) -> T;
+    fn visit_get_expr(&mut self, expr: &Expr) -> T;
     fn visit_grouping_expr(&mut self, expr: &Expr) -> T;
+    fn visit_interpolation_expr(&mut self, expr: &Expr) -> T;
     fn visit_literal_expr(&mut self, expr: &Expr) -> T;
+    fn visit_logical_expr(&mut self, expr: &Expr) -> T;
+    fn visit_set_expr(&mut self, expr: &Expr) -> T;
+    fn visit_super_expr(&mut self, expr: &Expr) -> T;
+    fn visit_this_expr(&mut self, expr: &Expr) -> T;
     fn visit_unary_expr(&mut self, expr: &Expr) -> T;
+    fn visit_variable_expr(&mut self, expr: &Expr) -> T;
 }
 
 impl Expr {
     pub fn accept<T>(&self, visitor: &mut dyn Visitor<T>) -> T {
         match self {
+            Expr::Assign(..) => visitor.visit_assign_expr(self),
             Expr::Binary(..) => visitor.visit_binary_expr(self),
+            Expr::Call(..) => visitor.visit_call_expr(self),
+            Expr::Get(..) => visitor.visit_get_expr(self),
             Expr::Grouping(..) => visitor.visit_grouping_expr(self),
+            Expr::Interpolation(..) => visitor.visit_interpolation_expr(self),
             Expr::Literal(..) => visitor.visit_literal_expr(self),
+            Expr::Logical(..) => visitor.visit_logical_expr(self),
+            Expr::Set(..) => visitor.visit_set_expr(self),
+            Expr::Super(..) => visitor.visit_super_expr(self),
+            Expr::This(..) => visitor.visit_this_expr(self),
             Expr::Unary(..) => visitor.visit_unary_expr(self),
+            Expr::Variable(..) => visitor.visit_variable_expr(self),
+        }
+    }
+}
+
+pub trait StmtVisitor<T> {
+    fn visit_block_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_class_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_expression_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_function_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_if_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_print_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_return_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_throw_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_try_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_var_stmt(&mut self, stmt: &Stmt) -> T;
+    fn visit_while_stmt(&mut self, stmt: &Stmt) -> T;
+}
+
+impl Stmt {
+    pub fn accept<T>(&self, visitor: &mut dyn StmtVisitor<T>) -> T {
+        match self {
+            Stmt::Block(..) => visitor.visit_block_stmt(self),
+            Stmt::Class(..) => visitor.visit_class_stmt(self),
+            Stmt::Expression(..) => visitor.visit_expression_stmt(self),
+            Stmt::Function(..) => visitor.visit_function_stmt(self),
+            Stmt::If(..) => visitor.visit_if_stmt(self),
+            Stmt::Print(..) => visitor.visit_print_stmt(self),
+            Stmt::Return(..) => visitor.visit_return_stmt(self),
+            Stmt::Throw(..) => visitor.visit_throw_stmt(self),
+            Stmt::Try(..) => visitor.visit_try_stmt(self),
+            Stmt::Var(..) => visitor.visit_var_stmt(self),
+            Stmt::While(..) => visitor.visit_while_stmt(self),
         }
     }
 }
 
+#[allow(dead_code)]
 pub struct AstPrinter;
 
+#[allow(dead_code)]
 impl AstPrinter {
     pub fn print(&mut self, expr: &Expr) -> String {
         expr.accept(self)
@@ -43,22 +136,48 @@ impl AstPrinter {
 
     fn parenthesize(&mut self, name: &str, exprs: &[&Expr]) -> String {
         let mut builder = String::new();
-        builder.push_str("(");
+        builder.push('(');
         builder.push_str(name);
         for expr in exprs {
-            builder.push_str(" ");
+            builder.push(' ');
             builder.push_str(&expr.accept(self));
         }
 
-        builder.push_str(")");
+        builder.push(')');
         builder
     }
 }
 
 impl Visitor<String> for AstPrinter {
+    fn visit_assign_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Assign(_, name, value) = expr {
+            self.parenthesize(&format!("= {}", name.lexeme), &[value])
+        } else {
+            todo!("not implemented")
+        }
+    }
+
     fn visit_binary_expr(&mut self, expr: &Expr) -> String {
         if let Expr::Binary(left, operator, right) = expr {
-            self.parenthesize(&operator.lexeme, &[&left, &right])
+            self.parenthesize(&operator.lexeme, &[left, right])
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_call_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Call(callee, _, arguments) = expr {
+            let mut exprs: Vec<&Expr> = vec![callee];
+            exprs.extend(arguments);
+            self.parenthesize("call", &exprs)
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Get(object, name) = expr {
+            self.parenthesize(&format!(". {}", name.lexeme), &[object])
         } else {
             todo!("not implemented")
         }
@@ -66,7 +185,16 @@ impl Visitor<String> for AstPrinter {
 
     fn visit_grouping_expr(&mut self, expr: &Expr) -> String {
         if let Expr::Grouping(expression) = expr {
-            self.parenthesize("group", &[&expression])
+            self.parenthesize("group", &[expression])
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_interpolation_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Interpolation(parts) = expr {
+            let parts: Vec<&Expr> = parts.iter().collect();
+            self.parenthesize("interpolate", &parts)
         } else {
             todo!("not implemented")
         }
@@ -86,9 +214,45 @@ impl Visitor<String> for AstPrinter {
         }
     }
 
+    fn visit_logical_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Logical(left, operator, right) = expr {
+            self.parenthesize(&operator.lexeme, &[left, right])
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Set(object, name, value) = expr {
+            self.parenthesize(&format!("= {}", name.lexeme), &[object, value])
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_super_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Super(_, _, method) = expr {
+            format!("(super {})", method.lexeme)
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_this_expr(&mut self, _expr: &Expr) -> String {
+        String::from("this")
+    }
+
     fn visit_unary_expr(&mut self, expr: &Expr) -> String {
         if let Expr::Unary(operator, right) = expr {
-            self.parenthesize(&operator.lexeme, &[&right])
+            self.parenthesize(&operator.lexeme, &[right])
+        } else {
+            todo!("not implemented")
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Expr) -> String {
+        if let Expr::Variable(_, name) = expr {
+            name.lexeme.clone()
         } else {
             todo!("not implemented")
         }
@@ -98,7 +262,7 @@ impl Visitor<String> for AstPrinter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::token::TokenType;
+    use crate::token::{Span, TokenType};
 
     #[test]
     fn test_ast_printer_literal() {
@@ -111,7 +275,7 @@ mod tests {
     fn test_ast_printer_binary_expression() {
         let expression = Expr::Binary(
             Box::new(Expr::Literal(LiteralValue::Number(1.0))),
-            Token::new(TokenType::Plus, "+".into(), None, 1),
+            Token::new(TokenType::Plus, "+".into(), None, 1, 3, Span::new(2, 3)),
             Box::new(Expr::Literal(LiteralValue::Number(2.0))),
         );
 
@@ -1,20 +1,66 @@
-use crate::expression::{Expr, LiteralValue, Visitor};
-use crate::runtime_error;
-use crate::runtime_error::RuntimeError;
+use crate::environment::Environment;
+use crate::error_code::ErrorCode;
+use crate::expression::{Depth, Expr, LiteralValue, Stmt, StmtVisitor, Visitor};
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_class::{LoxClass, Method};
+use crate::lox_function::LoxFunction;
+use crate::lox_instance::LoxInstance;
+use crate::native_function::{self, NativeFn, NativeFunction};
+use crate::runtime_error::{CallFrame, RuntimeError};
 use crate::token::{Token, TokenType};
-use crate::{Lox, expression};
+use crate::vm::{BoundMethod, Closure};
+use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
+use std::io::{self, Write};
 use std::ops::{Add, Div, Mul, Neg, Not, Sub};
+use std::rc::Rc;
 
-#[derive(PartialEq, Clone)]
-enum LoxValue {
+#[derive(Clone)]
+pub enum LoxValue {
     Number(f64),
     String(String),
     Boolean(bool),
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    /// A function compiled for the bytecode VM.
+    Closure(Rc<Closure>),
+    /// A method of a class compiled for the bytecode VM, bound to an instance.
+    BoundMethod(Rc<BoundMethod>),
     Nil,
 }
 
+/// Non-local exits that unwind through statement execution.
+pub enum Unwind {
+    Return(LoxValue),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Self {
+        Unwind::Error(err)
+    }
+}
+
+impl PartialEq for LoxValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Number(l), Self::Number(r)) => l == r,
+            (Self::String(l), Self::String(r)) => l == r,
+            (Self::Boolean(l), Self::Boolean(r)) => l == r,
+            (Self::Callable(l), Self::Callable(r)) => Rc::ptr_eq(l, r),
+            (Self::Class(l), Self::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Self::Instance(r)) => Rc::ptr_eq(l, r),
+            (Self::Closure(l), Self::Closure(r)) => Rc::ptr_eq(l, r),
+            (Self::BoundMethod(l), Self::BoundMethod(r)) => Rc::ptr_eq(l, r),
+            (Self::Nil, Self::Nil) => true,
+            _ => false,
+        }
+    }
+}
+
 impl PartialOrd for LoxValue {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         match (self, other) {
@@ -29,16 +75,23 @@ impl PartialOrd for LoxValue {
 
 impl LoxValue {
     fn is_numerical(&self) -> bool {
-        match self {
-            LoxValue::Number(_) => true,
-            _ => false,
-        }
+        matches!(self, LoxValue::Number(_))
     }
 
     fn is_string(&self) -> bool {
+        matches!(self, LoxValue::String(_))
+    }
+
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, LoxValue::Nil | LoxValue::Boolean(false))
+    }
+}
+
+impl fmt::Debug for LoxValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            LoxValue::String(_) => true,
-            _ => false,
+            LoxValue::String(s) => write!(f, "{:?}", s),
+            _ => write!(f, "{}", self),
         }
     }
 }
@@ -49,55 +102,138 @@ impl fmt::Display for LoxValue {
             LoxValue::Number(n) => write!(f, "{}", n),
             LoxValue::String(s) => write!(f, "{}", s),
             LoxValue::Boolean(b) => write!(f, "{}", b),
+            LoxValue::Callable(c) => write!(f, "{}", c),
+            LoxValue::Class(c) => write!(f, "{}", c),
+            LoxValue::Instance(i) => write!(f, "{}", i.borrow()),
+            LoxValue::Closure(c) => write!(f, "{}", c),
+            LoxValue::BoundMethod(m) => write!(f, "{}", m),
             LoxValue::Nil => write!(f, "nil"),
         }
     }
 }
 
-pub struct Interpreter<'a> {
-    lox: &'a mut Lox,
+/// How deeply calls may nest before a script is stopped with a catchable
+/// error rather than overflowing the host's stack. Each call costs the
+/// tree-walker several Rust frames, so reaching this takes far more than the
+/// 2 MiB Rust gives a spawned thread; see [`crate::STACK_SIZE`].
+pub const MAX_CALL_DEPTH: usize = 1024;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    frames: Vec<CallFrame>,
+    /// The class of the values `catch` binds for the interpreter's own errors.
+    error_class: Rc<LoxClass>,
+    output: Box<dyn Write>,
 }
 
-impl<'a> Interpreter<'a> {
-    pub fn new(lox: &'a mut Lox) -> Interpreter<'a> {
-        Interpreter { lox }
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Interpreter {
+            globals: globals.clone(),
+            environment: globals,
+            frames: Vec::new(),
+            error_class: Rc::new(LoxClass::new(String::from("Error"), None, HashMap::new())),
+            output: Box::new(io::stdout()),
+        };
+
+        interpreter.define_native("clock", Arity::Fixed(0), Box::new(native_function::clock));
+        interpreter
+    }
+
+    /// Redirects what `print` statements write.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn define_native(&mut self, name: &str, arity: Arity, function: Box<NativeFn>) {
+        let native = NativeFunction::new(name, arity, function);
+        self.globals
+            .borrow_mut()
+            .define(name, LoxValue::Callable(Rc::new(native)));
     }
 }
 
-impl<'a> Interpreter<'a> {
+impl Interpreter {
     fn stringify(&mut self, value: &LoxValue) -> String {
         match value {
             LoxValue::Number(n) => format!("{}", n),
-            LoxValue::String(s) => format!("{}", s),
+            LoxValue::String(s) => s.to_string(),
             LoxValue::Boolean(b) => format!("{}", b),
+            LoxValue::Callable(c) => c.to_string(),
+            LoxValue::Class(c) => c.to_string(),
+            LoxValue::Instance(i) => i.borrow().to_string(),
+            LoxValue::Closure(c) => c.to_string(),
+            LoxValue::BoundMethod(m) => m.to_string(),
             LoxValue::Nil => String::from("nil"),
         }
     }
 
-    pub fn interpret(&mut self, expr: &Expr) {
-        match self.evaluate(expr) {
-            Ok(lox_value) => {
-                println!("{}", self.stringify(&lox_value));
-            }
-            Err(err) => self.lox.error_runtime(err),
+    /// Executes `statements`, returning the value of the last one if it is an
+    /// expression statement.
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<LoxValue, RuntimeError> {
+        let mut value = LoxValue::Nil;
+        for statement in statements {
+            value = match statement {
+                Stmt::Expression(expression) => self.evaluate(expression)?,
+                _ => match self.execute(statement) {
+                    Ok(()) => LoxValue::Nil,
+                    Err(Unwind::Return(_)) => return Ok(LoxValue::Nil),
+                    Err(Unwind::Error(err)) => return Err(err),
+                },
+            };
         }
+
+        Ok(value)
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        stmt.accept(self)
+    }
+
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)));
+
+        let result = statements
+            .iter()
+            .try_for_each(|statement| self.execute(statement));
+
+        self.environment = previous;
+        result
     }
 
     fn evaluate(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
         expr.accept(self)
     }
 
+    fn look_up_variable(&self, name: &Token, depth: &Depth) -> Result<LoxValue, RuntimeError> {
+        match depth.get() {
+            Some(distance) => Ok(self
+                .environment
+                .borrow()
+                .get_at(distance, &name.lexeme)
+                .expect("resolved variables are always defined")),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
     fn check_number_operand(
         &mut self,
         operator: &Token,
         operand: &LoxValue,
     ) -> Result<(), RuntimeError> {
-        if let LoxValue::Number(n) = operand {
+        if let LoxValue::Number(_) = operand {
             return Ok(());
         }
 
         Err(RuntimeError::new(
             operator.clone(),
+            ErrorCode::OperandType,
             "Operand must be number.",
         ))
     }
@@ -108,14 +244,13 @@ impl<'a> Interpreter<'a> {
         left: &LoxValue,
         right: &LoxValue,
     ) -> Result<(), RuntimeError> {
-        if let LoxValue::Number(n) = left {
-            if let LoxValue::Number(n) = right {
-                return Ok(());
-            }
+        if let (LoxValue::Number(_), LoxValue::Number(_)) = (left, right) {
+            return Ok(());
         }
 
         Err(RuntimeError::new(
             operator.clone(),
+            ErrorCode::OperandType,
             "Operands must be numbers.",
         ))
     }
@@ -124,11 +259,7 @@ impl<'a> Interpreter<'a> {
 impl Not for LoxValue {
     type Output = Self;
     fn not(self) -> Self::Output {
-        match self {
-            LoxValue::Boolean(b) => LoxValue::Boolean(!b),
-            LoxValue::Nil => LoxValue::Boolean(true),
-            _ => LoxValue::Boolean(false),
-        }
+        LoxValue::Boolean(!self.is_truthy())
     }
 }
 
@@ -137,10 +268,8 @@ impl Neg for LoxValue {
     fn neg(self) -> Self::Output {
         match self {
             LoxValue::Number(n) => LoxValue::Number(-n),
-            LoxValue::String(s) => todo!(),
-            LoxValue::Boolean(true) => LoxValue::Boolean(false),
-            LoxValue::Boolean(false) => LoxValue::Boolean(false),
-            LoxValue::Nil => todo!(),
+            // The interpreter checks the operand before negating it.
+            _ => LoxValue::Nil,
         }
     }
 }
@@ -191,7 +320,32 @@ impl Mul for LoxValue {
     }
 }
 
-impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
+impl Visitor<Result<LoxValue, RuntimeError>> for Interpreter {
+    fn visit_assign_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Assign(depth, name, value) = expr else {
+            unreachable!()
+        };
+
+        let value = self.evaluate(value)?;
+        match depth.get() {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(distance, name, value.clone());
+            }
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+        Ok(value)
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Variable(depth, name) = expr else {
+            unreachable!()
+        };
+
+        self.look_up_variable(name, depth)
+    }
+
     fn visit_literal_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
         let Expr::Literal(value) = expr else {
             unreachable!()
@@ -206,6 +360,80 @@ impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
         }
     }
 
+    fn visit_call_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Call(callee, paren, arguments) = expr else {
+            unreachable!()
+        };
+
+        let callee = self.evaluate(callee)?;
+
+        let mut values = Vec::new();
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        let function: Rc<dyn LoxCallable> = match callee {
+            LoxValue::Callable(function) => function,
+            LoxValue::Class(class) => Rc::new(class),
+            _ => {
+                return Err(RuntimeError::new(
+                    paren.clone(),
+                    ErrorCode::NotCallable,
+                    "Can only call functions and classes.",
+                ));
+            }
+        };
+
+        if let Arity::Fixed(arity) = function.arity()
+            && values.len() != arity
+        {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                ErrorCode::ArityMismatch,
+                &format!("Expected {} arguments but got {}.", arity, values.len()),
+            ));
+        }
+
+        if self.frames.len() == MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(
+                paren.clone(),
+                ErrorCode::StackOverflow,
+                "Stack overflow.",
+            ));
+        }
+
+        self.frames.push(CallFrame {
+            function: function.name().to_string(),
+            call_site: paren.clone(),
+        });
+        let mut result = function.call(self, paren, values);
+        // The first frame an error unwinds through is the innermost one, so
+        // the stack is still complete there.
+        if let Err(err) = &mut result
+            && err.trace.is_empty()
+        {
+            err.trace = self.frames.iter().rev().cloned().collect();
+        }
+        self.frames.pop();
+
+        result
+    }
+
+    fn visit_get_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Get(object, name) = expr else {
+            unreachable!()
+        };
+
+        match self.evaluate(object)? {
+            LoxValue::Instance(instance) => LoxInstance::get(&instance, name),
+            _ => Err(RuntimeError::new(
+                name.clone(),
+                ErrorCode::NotAnInstance,
+                "Only instances have properties.",
+            )),
+        }
+    }
+
     fn visit_grouping_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
         let Expr::Grouping(expression) = expr else {
             unreachable!()
@@ -213,6 +441,89 @@ impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
         self.evaluate(expression)
     }
 
+    fn visit_interpolation_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Interpolation(parts) = expr else {
+            unreachable!()
+        };
+
+        let mut string = String::new();
+        for part in parts {
+            let value = self.evaluate(part)?;
+            string.push_str(&self.stringify(&value));
+        }
+        Ok(LoxValue::String(string))
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Logical(left, operator, right) = expr else {
+            unreachable!()
+        };
+
+        let left = self.evaluate(left)?;
+
+        if operator.token_type == TokenType::Or {
+            if left.is_truthy() {
+                return Ok(left);
+            }
+        } else if !left.is_truthy() {
+            return Ok(left);
+        }
+
+        self.evaluate(right)
+    }
+
+    fn visit_set_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Set(object, name, value) = expr else {
+            unreachable!()
+        };
+
+        let LoxValue::Instance(instance) = self.evaluate(object)? else {
+            return Err(RuntimeError::new(
+                name.clone(),
+                ErrorCode::NotAnInstance,
+                "Only instances have fields.",
+            ));
+        };
+
+        let value = self.evaluate(value)?;
+        instance.borrow_mut().set(name, value.clone());
+        Ok(value)
+    }
+
+    fn visit_super_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::Super(depth, _, method) = expr else {
+            unreachable!()
+        };
+
+        let distance = depth.get().expect("'super' is always resolved");
+        let environment = self.environment.borrow();
+        let Some(LoxValue::Class(superclass)) = environment.get_at(distance, "super") else {
+            unreachable!()
+        };
+
+        // `this` is bound in the environment just inside the one holding `super`.
+        let Some(LoxValue::Instance(object)) = environment.get_at(distance - 1, "this") else {
+            unreachable!()
+        };
+
+        match superclass.find_method(&method.lexeme) {
+            Some(method) => Ok(method.bind(object)),
+            None => Err(RuntimeError::new(
+                method.clone(),
+                ErrorCode::UndefinedProperty,
+                &format!("Undefined property '{}'.", method.lexeme),
+            )),
+        }
+    }
+
+    fn visit_this_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
+        let Expr::This(depth, keyword) = expr else {
+            unreachable!()
+        };
+
+        self.look_up_variable(keyword, depth)
+    }
+
     fn visit_unary_expr(&mut self, expr: &Expr) -> Result<LoxValue, RuntimeError> {
         let Expr::Unary(operator, expression) = expr else {
             unreachable!()
@@ -221,6 +532,8 @@ impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
         let right = self.evaluate(expression)?;
         match operator.token_type {
             TokenType::Minus => {
+                self.check_number_operand(operator, &right)?;
+
                 return Ok(-right);
             }
 
@@ -242,7 +555,7 @@ impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
         let right = self.evaluate(right)?;
         match operator.token_type {
             TokenType::Minus => {
-                self.check_number_operand(operator, &right)?;
+                self.check_number_operands(operator, &left, &right)?;
 
                 return Ok(left - right);
             }
@@ -269,6 +582,7 @@ impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
 
                 return Err(RuntimeError::new(
                     operator.clone(),
+                    ErrorCode::OperandType,
                     "Operands must be two numbers or two strings.",
                 ));
             }
@@ -300,6 +614,197 @@ impl<'a> Visitor<Result<LoxValue, RuntimeError>> for Interpreter<'a> {
             _ => {}
         }
 
-        todo!()
+        unreachable!()
+    }
+}
+
+impl StmtVisitor<Result<(), Unwind>> for Interpreter {
+    fn visit_block_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Block(statements) = stmt else {
+            unreachable!()
+        };
+
+        let environment = Environment::new_enclosed(self.environment.clone());
+        self.execute_block(statements, environment)
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Class(name, superclass, methods) = stmt else {
+            unreachable!()
+        };
+
+        let superclass = match superclass {
+            Some(expr) => {
+                let LoxValue::Class(class) = self.evaluate(expr)? else {
+                    let Expr::Variable(_, superclass_name) = expr else {
+                        unreachable!()
+                    };
+                    return Err(RuntimeError::new(
+                        superclass_name.clone(),
+                        ErrorCode::SuperclassNotClass,
+                        "Superclass must be a class.",
+                    )
+                    .into());
+                };
+                Some(class)
+            }
+            None => None,
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(&name.lexeme, LoxValue::Nil);
+
+        let enclosing = superclass.as_ref().map(|superclass| {
+            let mut environment = Environment::new_enclosed(self.environment.clone());
+            environment.define("super", LoxValue::Class(superclass.clone()));
+            std::mem::replace(&mut self.environment, Rc::new(RefCell::new(environment)))
+        });
+
+        let mut class_methods = HashMap::new();
+        for method in methods {
+            let function = LoxFunction::new(
+                method.clone(),
+                self.environment.clone(),
+                method.name.lexeme == "init",
+            );
+            class_methods.insert(
+                method.name.lexeme.clone(),
+                Method::Function(Rc::new(function)),
+            );
+        }
+
+        let class = LoxClass::new(name.lexeme.clone(), superclass, class_methods);
+
+        if let Some(enclosing) = enclosing {
+            self.environment = enclosing;
+        }
+
+        self.environment
+            .borrow_mut()
+            .assign(name, LoxValue::Class(Rc::new(class)))?;
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Expression(expression) = stmt else {
+            unreachable!()
+        };
+
+        self.evaluate(expression)?;
+        Ok(())
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Function(declaration) = stmt else {
+            unreachable!()
+        };
+
+        let function = LoxFunction::new(declaration.clone(), self.environment.clone(), false);
+        self.environment.borrow_mut().define(
+            &declaration.name.lexeme,
+            LoxValue::Callable(Rc::new(function)),
+        );
+        Ok(())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::If(_, condition, then_branch, else_branch) = stmt else {
+            unreachable!()
+        };
+
+        if self.evaluate(condition)?.is_truthy() {
+            self.execute(then_branch)?;
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Print(keyword, expression) = stmt else {
+            unreachable!()
+        };
+
+        let value = self.evaluate(expression)?;
+        let text = self.stringify(&value);
+        writeln!(self.output, "{}", text)
+            .map_err(|err| RuntimeError::output(keyword.clone(), &err))?;
+        Ok(())
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Return(_, value) = stmt else {
+            unreachable!()
+        };
+
+        let value = match value {
+            Some(value) => self.evaluate(value)?,
+            None => LoxValue::Nil,
+        };
+
+        Err(Unwind::Return(value))
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Throw(keyword, value) = stmt else {
+            unreachable!()
+        };
+
+        let value = self.evaluate(value)?;
+        Err(RuntimeError::thrown(keyword.clone(), value).into())
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Try(body, catch, finally) = stmt else {
+            unreachable!()
+        };
+
+        let environment = Environment::new_enclosed(self.environment.clone());
+        let mut result = self.execute_block(body, environment);
+
+        if let Some((name, body)) = catch
+            && let Err(Unwind::Error(err)) = result
+        {
+            let mut environment = Environment::new_enclosed(self.environment.clone());
+            environment.define(&name.lexeme, err.into_value(self.error_class.clone()));
+            result = self.execute_block(body, environment);
+        }
+
+        // A `finally` block always runs, and its own return or error takes
+        // precedence over whatever the rest of the statement produced.
+        if let Some(finally) = finally {
+            let environment = Environment::new_enclosed(self.environment.clone());
+            self.execute_block(finally, environment)?;
+        }
+
+        result
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::Var(name, initializer) = stmt else {
+            unreachable!()
+        };
+
+        let value = match initializer {
+            Some(initializer) => self.evaluate(initializer)?,
+            None => LoxValue::Nil,
+        };
+
+        self.environment.borrow_mut().define(&name.lexeme, value);
+        Ok(())
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        let Stmt::While(_, condition, body) = stmt else {
+            unreachable!()
+        };
+
+        while self.evaluate(condition)?.is_truthy() {
+            self.execute(body)?;
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,859 @@
+mod bytecode_file;
+mod chunk;
+mod compiler;
+mod diagnostic;
+mod disassembler;
+mod environment;
+mod error_code;
+mod expression;
+mod interpreter;
+mod lox_callable;
+mod lox_class;
+mod lox_error;
+mod lox_function;
+mod lox_instance;
+mod native_function;
+mod optimizer;
+mod output;
+mod parser;
+mod resolver;
+mod runtime_error;
+mod scanner;
+pub mod syntax;
+mod token;
+mod value;
+mod vm;
+
+use crate::chunk::Function;
+use crate::compiler::Compiler;
+use crate::diagnostic::SourceMap;
+use crate::expression::Stmt;
+use crate::interpreter::{Interpreter, LoxValue};
+use crate::optimizer::Optimizer;
+use crate::vm::Vm;
+use parser::Parser;
+use resolver::Resolver;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub use crate::diagnostic::{Diagnostic, Label, Severity};
+pub use crate::error_code::ErrorCode;
+pub use crate::lox_callable::Arity;
+pub use crate::lox_error::LoxError;
+pub use crate::output::SharedBuffer;
+pub use crate::runtime_error::{CallFrame, RuntimeError};
+pub use crate::scanner::{LexError, Scanner};
+pub use crate::token::{Literal, Span, Token, TokenType, Trivia, TriviaKind};
+pub use crate::value::{Object, Value};
+
+/// The thread stack the tree-walking backend needs to nest calls as deeply
+/// as it allows. Hosts that run deeply recursive scripts on
+/// [`Backend::TreeWalk`] should do so on a thread with this much stack, as the
+/// `lox` binary does; the bytecode VM does not use the host's stack for calls.
+pub const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+/// How diagnostics are written to the error sink.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ErrorFormat {
+    /// Source excerpts with caret underlines, for people.
+    #[default]
+    Human,
+    /// One JSON object per line, for tools.
+    Json,
+}
+
+/// How programs are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Backend {
+    /// Walks the syntax tree directly.
+    #[default]
+    TreeWalk,
+    /// Compiles to bytecode and runs it on a stack machine.
+    Vm,
+}
+
+enum Engine {
+    TreeWalk(Interpreter),
+    Vm(Vm),
+}
+
+/// An embeddable Lox interpreter. Global state persists between calls to
+/// [`Lox::eval`], so a host can feed it a program piece by piece.
+pub struct Lox {
+    engine: Engine,
+    had_error: bool,
+    had_runtime_error: bool,
+    errors: Vec<Diagnostic>,
+    error_output: Box<dyn Write>,
+    error_format: ErrorFormat,
+    warnings: bool,
+    sources: SourceMap,
+}
+
+impl Default for Lox {
+    fn default() -> Self {
+        Lox::new()
+    }
+}
+
+impl Lox {
+    pub fn new() -> Lox {
+        Lox::with_backend(Backend::default())
+    }
+
+    pub fn with_backend(backend: Backend) -> Lox {
+        let engine = match backend {
+            Backend::TreeWalk => Engine::TreeWalk(Interpreter::new()),
+            Backend::Vm => Engine::Vm(Vm::new()),
+        };
+
+        Lox {
+            engine,
+            had_error: false,
+            had_runtime_error: false,
+            errors: Vec::new(),
+            error_output: Box::new(io::stderr()),
+            error_format: ErrorFormat::Human,
+            warnings: true,
+            sources: SourceMap::default(),
+        }
+    }
+
+    /// Sets where program output from `print` goes. Defaults to stdout.
+    pub fn set_output<W: Write + 'static>(&mut self, output: W) {
+        match &mut self.engine {
+            Engine::TreeWalk(interpreter) => interpreter.set_output(Box::new(output)),
+            Engine::Vm(vm) => vm.set_output(Box::new(output)),
+        }
+    }
+
+    /// Sets where diagnostics are written as they are reported. Defaults to
+    /// stderr.
+    pub fn set_error_output<W: Write + 'static>(&mut self, error_output: W) {
+        self.error_output = Box::new(error_output);
+    }
+
+    pub fn set_error_format(&mut self, error_format: ErrorFormat) {
+        self.error_format = error_format;
+    }
+
+    /// Controls whether warnings, such as those for unreachable code, are
+    /// reported. They are on by default and never stop a program from
+    /// running.
+    pub fn set_warnings(&mut self, warnings: bool) {
+        self.warnings = warnings;
+    }
+
+    /// Releases the text of every source run so far, which is otherwise kept
+    /// so diagnostics can quote it. Hosts that call [`Lox::eval`] in a loop
+    /// can call this between runs to bound memory; later diagnostics about
+    /// code from a released source are reported without a source excerpt.
+    pub fn clear_sources(&mut self) {
+        self.sources.clear();
+    }
+
+    /// Runs `source` and returns the value of its last statement when that
+    /// statement is an expression, or `nil` otherwise.
+    pub fn eval(&mut self, source: &str) -> Result<Value, LoxError> {
+        self.run("<input>", source)
+    }
+
+    pub fn run_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, LoxError> {
+        let content = fs::read_to_string(&path)?;
+        self.run(&path.as_ref().display().to_string(), &content)
+    }
+
+    fn run(&mut self, name: &str, source: &str) -> Result<Value, LoxError> {
+        self.had_error = false;
+        self.had_runtime_error = false;
+
+        let offset = self.sources.add(name, source);
+        let statements = self.parse(source, offset)?;
+        let result = match &mut self.engine {
+            Engine::TreeWalk(interpreter) => interpreter.interpret(&statements),
+            Engine::Vm(_) => {
+                let function = self.compile(&statements)?;
+                let Engine::Vm(vm) = &mut self.engine else {
+                    unreachable!()
+                };
+                vm.interpret(function)
+            }
+        };
+
+        result.map(Value::from).map_err(|err| {
+            self.error_runtime(&err);
+            LoxError::Runtime(err)
+        })
+    }
+
+    /// Scans, parses and resolves `source`, stopping at the first phase that
+    /// reports an error, then optimizes the resulting statements.
+    fn parse(&mut self, source: &str, offset: usize) -> Result<Vec<Stmt>, LoxError> {
+        // Warnings from an earlier successful run are never taken, so they
+        // would otherwise end up in the next compile error.
+        self.errors.clear();
+        let tokens = Scanner::with_offset(source, offset);
+
+        let statements = Parser::new(tokens, self).parse();
+        if self.had_error {
+            return Err(LoxError::Compile(std::mem::take(&mut self.errors)));
+        }
+
+        Resolver::new(self).resolve(&statements);
+        if self.had_error {
+            return Err(LoxError::Compile(std::mem::take(&mut self.errors)));
+        }
+
+        let statements = Optimizer::new(self).optimize(&statements);
+        Ok(statements)
+    }
+
+    fn compile(&mut self, statements: &[Stmt]) -> Result<Function, LoxError> {
+        let function = Compiler::new(self).compile(statements);
+        if self.had_error {
+            return Err(LoxError::Compile(std::mem::take(&mut self.errors)));
+        }
+        Ok(function)
+    }
+
+    /// Compiles `source` to bytecode without running it and returns the
+    /// listing of every function in it.
+    pub fn disassemble(&mut self, source: &str) -> Result<String, LoxError> {
+        self.disassemble_source("<input>", source)
+    }
+
+    pub fn disassemble_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String, LoxError> {
+        let content = fs::read_to_string(&path)?;
+        self.disassemble_source(&path.as_ref().display().to_string(), &content)
+    }
+
+    fn disassemble_source(&mut self, name: &str, source: &str) -> Result<String, LoxError> {
+        self.had_error = false;
+        let offset = self.sources.add(name, source);
+        let statements = self.parse(source, offset)?;
+        let function = self.compile(&statements)?;
+        Ok(disassembler::disassemble(&function))
+    }
+
+    /// Compiles a script to the precompiled format that
+    /// [`Lox::run_bytecode`] loads, without running it.
+    pub fn compile_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u8>, LoxError> {
+        let name = path.as_ref().display().to_string();
+        let source = fs::read_to_string(&path)?;
+        self.had_error = false;
+
+        let offset = self.sources.add(&name, &source);
+        let statements = self.parse(&source, offset)?;
+        let function = self.compile(&statements)?;
+        Ok(bytecode_file::encode(&function, &name, &source, offset))
+    }
+
+    /// Runs a script precompiled by [`Lox::compile_file`] without scanning or
+    /// parsing it again. Only the VM backend can run bytecode.
+    pub fn run_bytecode(&mut self, bytes: &[u8]) -> Result<Value, LoxError> {
+        self.had_error = false;
+        self.had_runtime_error = false;
+
+        let Engine::Vm(vm) = &mut self.engine else {
+            return Err(LoxError::Bytecode(String::from(
+                "Bytecode can only run on the VM backend.",
+            )));
+        };
+        let sources = &mut self.sources;
+        let function = bytecode_file::decode(bytes, |name, source| sources.add(name, source))
+            .map_err(LoxError::Bytecode)?;
+
+        vm.interpret(function).map(Value::from).map_err(|err| {
+            self.error_runtime(&err);
+            LoxError::Runtime(err)
+        })
+    }
+
+    pub fn run_bytecode_file<P: AsRef<Path>>(&mut self, path: P) -> Result<Value, LoxError> {
+        let bytes = fs::read(path)?;
+        self.run_bytecode(&bytes)
+    }
+
+    /// Makes the VM print its stack and each instruction to the program
+    /// output as it runs. Has no effect on the tree-walker.
+    pub fn set_trace_execution(&mut self, trace_execution: bool) {
+        if let Engine::Vm(vm) = &mut self.engine {
+            vm.set_trace_execution(trace_execution);
+        }
+    }
+
+    /// Makes the VM print the listing of each script to the program output
+    /// before running it. Has no effect on the tree-walker.
+    pub fn set_dump_bytecode(&mut self, dump_bytecode: bool) {
+        if let Engine::Vm(vm) = &mut self.engine {
+            vm.set_dump_bytecode(dump_bytecode);
+        }
+    }
+
+    /// Exposes a Rust function to scripts as a global named `name`.
+    pub fn define_native<F>(&mut self, name: &str, arity: Arity, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RuntimeError> + 'static,
+    {
+        let function = move |arguments: &[LoxValue]| {
+            let arguments: Vec<_> = arguments.iter().cloned().map(Value::from).collect();
+            function(&arguments).map(LoxValue::from)
+        };
+        match &mut self.engine {
+            Engine::TreeWalk(interpreter) => {
+                interpreter.define_native(name, arity, Box::new(function))
+            }
+            Engine::Vm(vm) => vm.define_native(name, arity, Box::new(function)),
+        }
+    }
+
+    pub fn had_error(&self) -> bool {
+        self.had_error
+    }
+
+    pub fn had_runtime_error(&self) -> bool {
+        self.had_runtime_error
+    }
+
+    fn error_parser(&mut self, token: &Token, code: ErrorCode, message: &str) {
+        self.report(Diagnostic::error(message, token.span, token.line).with_code(code));
+    }
+
+    fn error_runtime(&mut self, err: &RuntimeError) {
+        self.emit(&err.to_diagnostic());
+        self.had_runtime_error = true;
+    }
+
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if diagnostic.severity == Severity::Warning && !self.warnings {
+            return;
+        }
+        self.emit(&diagnostic);
+        if diagnostic.severity == Severity::Error {
+            self.had_error = true;
+        }
+        self.errors.push(diagnostic);
+    }
+
+    fn emit(&mut self, diagnostic: &Diagnostic) {
+        let text = match self.error_format {
+            ErrorFormat::Human => diagnostic.render(&self.sources),
+            ErrorFormat::Json => diagnostic.to_json(&self.sources) + "\n",
+        };
+        // There is nowhere left to report a failing diagnostics sink.
+        let _ = write!(self.error_output, "{}", text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, thread};
+
+    const BACKENDS: [Backend; 2] = [Backend::TreeWalk, Backend::Vm];
+
+    /// An interpreter on `backend` that writes its program output and its
+    /// diagnostics to the returned buffers.
+    fn interpreter(backend: Backend) -> (Lox, SharedBuffer, SharedBuffer) {
+        let output = SharedBuffer::new();
+        let diagnostics = SharedBuffer::new();
+        let mut lox = Lox::with_backend(backend);
+        lox.set_output(output.clone());
+        lox.set_error_output(diagnostics.clone());
+        (lox, output, diagnostics)
+    }
+
+    /// Runs `source` on a fresh interpreter and returns what it printed along
+    /// with its result.
+    fn run(backend: Backend, source: &str) -> (String, Result<Value, LoxError>) {
+        let (mut lox, output, _) = interpreter(backend);
+        let result = lox.eval(source);
+        (output.contents(), result)
+    }
+
+    #[test]
+    fn test_eval_returns_last_expression_and_keeps_globals() {
+        for backend in BACKENDS {
+            let mut lox = Lox::with_backend(backend);
+            assert_eq!(lox.eval("var a = 1;").unwrap(), Value::Nil);
+            assert_eq!(lox.eval("a + 2;").unwrap(), Value::Number(3.0));
+        }
+    }
+
+    #[test]
+    fn test_functions_classes_and_instances_are_opaque_objects() {
+        for backend in BACKENDS {
+            let (mut lox, _, _) = interpreter(backend);
+            lox.eval("fun f() {} class A {} var a = A();").unwrap();
+
+            for (source, type_name, printed) in [
+                ("f;", "function", "<fn f>"),
+                ("clock;", "function", "<native fn clock>"),
+                ("A;", "class", "A"),
+                ("a;", "instance", "A instance"),
+            ] {
+                let Value::Object(object) = lox.eval(source).unwrap() else {
+                    panic!("expected an object");
+                };
+                assert_eq!(object.type_name(), type_name);
+                assert_eq!(object.to_string(), printed);
+            }
+            assert_eq!(lox.eval("a;").unwrap(), lox.eval("a;").unwrap());
+            assert_ne!(lox.eval("a;").unwrap(), lox.eval("A();").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_eval_reports_errors_without_exiting() {
+        for backend in BACKENDS {
+            let (mut lox, _, _) = interpreter(backend);
+            assert!(matches!(lox.eval("1 +;"), Err(LoxError::Compile(_))));
+            assert!(matches!(lox.eval("1 < nil;"), Err(LoxError::Runtime(_))));
+            assert!(lox.had_runtime_error());
+        }
+    }
+
+    #[test]
+    fn test_errors_carry_stable_codes() {
+        for backend in BACKENDS {
+            let (mut lox, _, _) = interpreter(backend);
+
+            let Err(LoxError::Compile(errors)) = lox.eval("var = 1;") else {
+                panic!("expected a compile error");
+            };
+            assert_eq!(errors[0].code, Some(ErrorCode::ExpectedName));
+
+            for source in ["-\"a\";", "\"a\" - 1;"] {
+                let Err(LoxError::Runtime(err)) = lox.eval(source) else {
+                    panic!("expected a runtime error");
+                };
+                assert_eq!(err.code, ErrorCode::OperandType);
+            }
+        }
+    }
+
+    #[test]
+    fn test_undefined_variables_are_reported_at_their_name() {
+        for source in [
+            "print 1;\nprint  undefinedVar;",
+            "print 1;\n  undefinedVar = 1;",
+        ] {
+            for backend in BACKENDS {
+                let Err(LoxError::Runtime(err)) = run(backend, source).1 else {
+                    panic!("expected a runtime error");
+                };
+                assert_eq!(err.code, ErrorCode::UndefinedVariable);
+                assert_eq!(err.message, "Undefined variable 'undefinedVar'.");
+                assert_eq!(err.token.line, 2);
+                let start = source.find("undefinedVar").unwrap();
+                assert_eq!(err.token.span, Span::new(start, start + 12));
+            }
+        }
+    }
+
+    #[test]
+    fn test_logical_operators_return_an_operand_and_short_circuit() {
+        let source = "var none = nil; var zero = 0;\n\
+                      print none or \"x\";\n\
+                      print zero and 2;\n\
+                      print none and 2;\n\
+                      print zero or 2;\n\
+                      fun say(value) { print value; return value; }\n\
+                      print say(false) and say(\"skipped\");\n\
+                      print say(1) or say(\"skipped\");";
+        for backend in BACKENDS {
+            let (output, result) = run(backend, source);
+            result.unwrap();
+            assert_eq!(output, "x\n2\nnil\n0\nfalse\nfalse\n1\n1\n");
+        }
+    }
+
+    #[test]
+    fn test_closures_keep_the_binding_they_resolved() {
+        let source = "var a = \"global\";\n\
+                      {\n\
+                        fun show() { print a; }\n\
+                        show();\n\
+                        var a = \"block\";\n\
+                        show();\n\
+                        print a;\n\
+                      }";
+        for backend in BACKENDS {
+            let (output, result) = run(backend, source);
+            result.unwrap();
+            assert_eq!(output, "global\nglobal\nblock\n");
+        }
+    }
+
+    #[test]
+    fn test_functions_return_values_and_close_over_variables() {
+        let source = "fun add(a, b) { return a + b; }\n\
+                      fun counter() { var n = 0; fun next() { n = n + 1; return n; } return next; }\n\
+                      fun nothing() { return; }\n\
+                      var next = counter();\n\
+                      next();\n\
+                      print add(1, 2);\n\
+                      print next();\n\
+                      print nothing();\n\
+                      print add;";
+        for backend in BACKENDS {
+            let (output, result) = run(backend, source);
+            result.unwrap();
+            assert_eq!(output, "3\n2\nnil\n<fn add>\n");
+        }
+    }
+
+    #[test]
+    fn test_arity_errors_point_at_the_closing_paren() {
+        let source = "fun f(a) {}\nf(1,\n  2);";
+        for backend in BACKENDS {
+            let Err(LoxError::Runtime(err)) = run(backend, source).1 else {
+                panic!("expected a runtime error");
+            };
+            assert_eq!(err.code, ErrorCode::ArityMismatch);
+            assert_eq!(err.message, "Expected 1 arguments but got 2.");
+            assert_eq!(err.token.line, 3);
+            assert_eq!(&source[err.token.span.start..err.token.span.end], ")");
+        }
+    }
+
+    #[test]
+    fn test_classes_initialize_instances_and_bind_this() {
+        let source = "class Point {\n\
+                        init(x, y) { this.x = x; this.y = y; }\n\
+                        sum() { return this.x + this.y; }\n\
+                      }\n\
+                      var p = Point(1, 2);\n\
+                      var sum = p.sum;\n\
+                      p.x = 10;\n\
+                      print sum();\n\
+                      print p.init(3, 4) == p;\n\
+                      print Point;\n\
+                      print p;";
+        for backend in BACKENDS {
+            let (output, result) = run(backend, source);
+            result.unwrap();
+            assert_eq!(output, "12\ntrue\nPoint\nPoint instance\n");
+
+            let Err(LoxError::Runtime(err)) = run(backend, "class A {}\nA().missing;").1 else {
+                panic!("expected a runtime error");
+            };
+            assert_eq!(err.code, ErrorCode::UndefinedProperty);
+            assert_eq!(err.message, "Undefined property 'missing'.");
+            assert_eq!(err.token.line, 2);
+        }
+    }
+
+    #[test]
+    fn test_super_is_only_allowed_in_a_subclass() {
+        let cases = [
+            ("super.f();", ErrorCode::SuperOutsideClass),
+            (
+                "class A { f() { super.f(); } }",
+                ErrorCode::SuperWithoutSuperclass,
+            ),
+        ];
+        for backend in BACKENDS {
+            for (source, code) in cases {
+                let Err(LoxError::Compile(errors)) = run(backend, source).1 else {
+                    panic!("expected a compile error");
+                };
+                assert_eq!(errors[0].code, Some(code));
+                let span = errors[0].span;
+                assert_eq!(&source[span.start..span.end], "super");
+            }
+
+            let (output, result) = run(
+                backend,
+                "class A { f() { return \"A\"; } }\n\
+                 class B < A { f() { return \"B\" + super.f(); } }\n\
+                 print B().f();",
+            );
+            result.unwrap();
+            assert_eq!(output, "BA\n");
+        }
+    }
+
+    #[test]
+    fn test_native_functions_are_callable_from_scripts() {
+        for backend in BACKENDS {
+            let (mut lox, _, _) = interpreter(backend);
+            lox.define_native("sum", Arity::Variadic, |args| {
+                args.iter()
+                    .try_fold(Value::Number(0.0), |total, arg| match (total, arg) {
+                        (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                        _ => Err(RuntimeError::native("sum() expects numbers.")),
+                    })
+            });
+
+            assert_eq!(lox.eval("sum(1, 2, 3);").unwrap(), Value::Number(6.0));
+            assert_eq!(lox.eval("sum();").unwrap(), Value::Number(0.0));
+            assert!(matches!(
+                lox.eval("clock() > 0;").unwrap(),
+                Value::Boolean(true)
+            ));
+
+            let Err(LoxError::Runtime(err)) = lox.eval("\nsum(1, nil);") else {
+                panic!("expected a runtime error");
+            };
+            assert_eq!(err.message, "sum() expects numbers.");
+            assert_eq!(err.token.line, 2);
+        }
+    }
+
+    #[test]
+    fn test_runtime_errors_carry_a_traceback() {
+        for backend in BACKENDS {
+            let (mut lox, _, _) = interpreter(backend);
+            let source =
+                "fun inner() {\n  return 1 < nil;\n}\nfun outer() {\n  inner();\n}\nouter();";
+
+            let Err(LoxError::Runtime(err)) = lox.eval(source) else {
+                panic!("expected a runtime error");
+            };
+            assert_eq!(
+                err.traceback(),
+                vec![
+                    (String::from("inner()"), 2),
+                    (String::from("outer()"), 5),
+                    (String::from("script"), 7),
+                ]
+            );
+
+            let Err(LoxError::Runtime(err)) = lox.eval("1 < nil;") else {
+                panic!("expected a runtime error");
+            };
+            assert!(err.traceback().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_runaway_recursion_raises_a_catchable_error() {
+        let source = "fun deep(n) { if (n == 0) return 0; return 1 + deep(n - 1); }\n\
+                      fun f() { f(); }\n\
+                      var message;\n\
+                      try { f(); } catch (e) { message = e.message; }";
+        let check = move || {
+            for backend in BACKENDS {
+                let (mut lox, _, _) = interpreter(backend);
+                lox.eval(source).unwrap();
+                assert_eq!(
+                    lox.eval("message;").unwrap(),
+                    Value::String(String::from("Stack overflow."))
+                );
+                assert_eq!(lox.eval("deep(1000);").unwrap(), Value::Number(1000.0));
+
+                let Err(LoxError::Runtime(err)) = lox.eval("deep(100000);") else {
+                    panic!("expected a runtime error");
+                };
+                assert_eq!(err.code, ErrorCode::StackOverflow);
+            }
+        };
+
+        // Like the binary, give the tree-walker the stack it needs.
+        let tester = thread::Builder::new().stack_size(STACK_SIZE).spawn(check);
+        tester.unwrap().join().unwrap();
+    }
+
+    #[test]
+    fn test_try_catches_runtime_errors_and_always_runs_finally() {
+        for backend in BACKENDS {
+            let (output, result) = run(
+                backend,
+                "fun f() {\n  try { return 1 < nil; } catch (e) { print e.message; print e.line; }\n}\n\
+                 f();\n\
+                 fun g() { try { return 1; } finally { print 2; } }\n\
+                 print g();\n\
+                 try { throw 3; } catch (e) { print e; }",
+            );
+            result.unwrap();
+            assert_eq!(output, "Operands must be numbers.\n2\n2\n1\n3\n");
+
+            let (_, Err(LoxError::Runtime(err))) = run(backend, "try { throw 4; } finally {}")
+            else {
+                panic!("expected an uncaught error");
+            };
+            assert_eq!(err.code, ErrorCode::UncaughtThrow);
+            assert_eq!(err.thrown_value(), Some(Value::Number(4.0)));
+        }
+    }
+
+    #[test]
+    fn test_interpolated_strings_stringify_each_segment() {
+        for backend in BACKENDS {
+            let mut lox = Lox::with_backend(backend);
+            lox.eval("var name = \"Lox\"; fun f() {}").unwrap();
+
+            assert_eq!(
+                lox.eval(r#""Hi ${name}! ${1 + 1} ${nil} ${f} ${"<${name}>"}";"#)
+                    .unwrap(),
+                Value::String(String::from("Hi Lox! 2 nil <fn f> <Lox>"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_closures_and_classes_agree_across_backends() {
+        let source = "fun counter() { var n = 0; fun inc() { n = n + 1; return n; } return inc; }\n\
+                      var c = counter(); c(); print c();\n\
+                      class A { init(x) { this.x = x; } show() { return \"A${this.x}\"; } }\n\
+                      class B < A { show() { fun wrap() { return \"B\" + super.show(); } return wrap(); } }\n\
+                      print B(1).show(); print B(2).init(3).x;\n\
+                      var leaked; { var v = \"kept\"; fun get() { return v; } leaked = get; }\n\
+                      print leaked();";
+
+        for backend in BACKENDS {
+            let (mut lox, output, _) = interpreter(backend);
+            lox.eval(source).unwrap();
+            assert_eq!(output.contents(), "2\nBA1\n3\nkept\n");
+
+            // Variables captured by a block an error unwound stay usable.
+            assert!(
+                lox.eval("{ var w = 1; fun f() { return w; } leaked = f; 1 < nil; }")
+                    .is_err()
+            );
+            assert_eq!(lox.eval("leaked();").unwrap(), Value::Number(1.0));
+        }
+    }
+
+    #[test]
+    fn test_too_many_interpolated_parts_is_a_compile_error() {
+        // Locals need no constants, so only the part count is too large.
+        let parts = "${a}".repeat(u16::MAX as usize + 1);
+        let source = format!("{{ var a; print \"{}\"; }}", parts);
+
+        let Err(LoxError::Compile(errors)) = run(Backend::Vm, &source).1 else {
+            panic!("expected a compile error");
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(ErrorCode::CompilerLimit));
+        assert_eq!(
+            errors[0].message,
+            "Too many parts in one interpolated string."
+        );
+    }
+
+    #[test]
+    fn test_trace_execution_shows_the_stack_before_each_instruction() {
+        let (mut lox, output, _) = interpreter(Backend::Vm);
+        lox.set_trace_execution(true);
+
+        lox.eval("print 1 + 2;").unwrap();
+        assert_eq!(
+            output.contents(),
+            "          [ <fn script> ]\n\
+             0000    1 Constant            0 '3'\n\
+             \x20         [ <fn script> ][ 3 ]\n\
+             0003    | Print\n\
+             3\n\
+             \x20         [ <fn script> ]\n\
+             0004    | Nil\n\
+             \x20         [ <fn script> ][ nil ]\n\
+             0005    | Return\n"
+        );
+    }
+
+    #[test]
+    fn test_precompiled_scripts_run_like_their_source() {
+        let path = env::temp_dir().join(format!("lox-precompiled-{}.lox", std::process::id()));
+        fs::write(
+            &path,
+            "fun greet(name) { print \"hi ${name}\"; }\ngreet(\"vm\");\n1 < nil;",
+        )
+        .unwrap();
+        let bytes = Lox::new().compile_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let (mut lox, output, diagnostics) = interpreter(Backend::Vm);
+        let Err(LoxError::Runtime(err)) = lox.run_bytecode(&bytes) else {
+            panic!("expected a runtime error");
+        };
+        assert_eq!(output.contents(), "hi vm\n");
+        assert_eq!(err.token.line, 3);
+        assert!(diagnostics.contents().contains("3 | 1 < nil;"));
+
+        assert!(matches!(
+            Lox::new().run_bytecode(&bytes),
+            Err(LoxError::Bytecode(_))
+        ));
+    }
+
+    #[test]
+    fn test_a_script_compiles_again_on_the_same_interpreter() {
+        let path = env::temp_dir().join(format!("lox-recompiled-{}.lox", std::process::id()));
+        fs::write(&path, "1;\nprint 2;").unwrap();
+        let mut lox = Lox::new();
+        let first = lox.compile_file(&path).unwrap();
+        let second = lox.compile_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_unreachable_code_is_removed_with_a_warning() {
+        let source = "fun f() {\n  return 1;\n  print \"after\";\n}\nprint f();\n\
+                      if (false) print \"dead\";\nwhile (1 > 2) print \"loop\";\n1; if (false) 2;";
+        for backend in BACKENDS {
+            let (mut lox, output, diagnostics) = interpreter(backend);
+            assert_eq!(lox.eval(source).unwrap(), Value::Nil);
+            assert!(!lox.had_error());
+            assert_eq!(output.contents(), "1\n");
+            let warnings = diagnostics.contents();
+            assert_eq!(
+                warnings
+                    .matches("warning[E0500]: Unreachable code.")
+                    .count(),
+                4
+            );
+            assert!(warnings.contains("3 |   print \"after\";"));
+
+            let quiet = SharedBuffer::new();
+            lox.set_error_output(quiet.clone());
+            lox.set_warnings(false);
+            lox.eval(source).unwrap();
+            assert_eq!(quiet.contents(), "");
+        }
+    }
+
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_a_failing_output_is_a_runtime_error() {
+        for backend in BACKENDS {
+            let mut lox = Lox::with_backend(backend);
+            lox.set_output(ClosedPipe);
+            lox.set_error_output(io::sink());
+
+            let Err(LoxError::Runtime(err)) = lox.eval("print 1;") else {
+                panic!("expected a runtime error");
+            };
+            assert_eq!(err.code, ErrorCode::OutputFailed);
+            assert_eq!(err.token.line, 1);
+        }
+    }
+
+    #[test]
+    fn test_output_and_diagnostics_go_to_their_sinks() {
+        for backend in BACKENDS {
+            let (mut lox, output, diagnostics) = interpreter(backend);
+            lox.eval("print 1 + 2; print nil;").unwrap();
+            assert_eq!(output.contents(), "3\nnil\n");
+            assert_eq!(diagnostics.contents(), "");
+
+            assert!(lox.eval("1 < nil;").is_err());
+            assert!(diagnostics.contents().contains("Operands must be numbers."));
+        }
+    }
+}
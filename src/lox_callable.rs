@@ -0,0 +1,30 @@
+use crate::interpreter::{Interpreter, LoxValue};
+use crate::native_function::NativeFunction;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use std::fmt;
+
+/// How many arguments a callable accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arity {
+    Fixed(usize),
+    Variadic,
+}
+
+pub trait LoxCallable: fmt::Display {
+    /// The name shown for this callable in stack traces.
+    fn name(&self) -> &str;
+    fn arity(&self) -> Arity;
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, RuntimeError>;
+
+    /// The bytecode VM can only call host functions, which need no
+    /// interpreter.
+    fn as_native(&self) -> Option<&NativeFunction> {
+        None
+    }
+}
@@ -0,0 +1,104 @@
+use crate::interpreter::{Interpreter, LoxValue};
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_function::LoxFunction;
+use crate::lox_instance::LoxInstance;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use crate::vm::{BoundMethod, Closure};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// A method as compiled by whichever backend defined its class.
+#[derive(Clone)]
+pub enum Method {
+    Function(Rc<LoxFunction>),
+    Closure(Rc<Closure>),
+}
+
+impl Method {
+    pub fn arity(&self) -> Arity {
+        match self {
+            Method::Function(function) => function.arity(),
+            Method::Closure(closure) => Arity::Fixed(closure.function.arity),
+        }
+    }
+
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxValue {
+        match self {
+            Method::Function(function) => LoxValue::Callable(Rc::new(function.bind(instance))),
+            Method::Closure(closure) => LoxValue::BoundMethod(Rc::new(BoundMethod {
+                receiver: instance,
+                method: closure.clone(),
+            })),
+        }
+    }
+}
+
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Method>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Method>,
+    ) -> LoxClass {
+        LoxClass {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn find_method(&self, name: &str) -> Option<Method> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(method.clone());
+        }
+
+        self.superclass
+            .as_ref()
+            .and_then(|superclass| superclass.find_method(name))
+    }
+}
+
+// Instances need a handle back to their class, so calls go through the `Rc`.
+impl LoxCallable for Rc<LoxClass> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn arity(&self) -> Arity {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => Arity::Fixed(0),
+        }
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, RuntimeError> {
+        let instance = Rc::new(RefCell::new(LoxInstance::new(self.clone())));
+        // Only classes declared by the tree-walker are ever called here.
+        if let Some(Method::Function(initializer)) = self.find_method("init") {
+            initializer
+                .bind(instance.clone())
+                .call(interpreter, paren, arguments)?;
+        }
+
+        Ok(LoxValue::Instance(instance))
+    }
+}
+
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::diagnostic::Diagnostic;
+use crate::runtime_error::RuntimeError;
+use std::{error, fmt, io};
+
+/// Everything that can go wrong while running a Lox program.
+#[derive(Debug)]
+pub enum LoxError {
+    /// The script could not be read.
+    Io(io::Error),
+    /// Scanning, parsing, or resolving failed; nothing was executed.
+    Compile(Vec<Diagnostic>),
+    /// Execution started but raised an error.
+    Runtime(RuntimeError),
+    /// A precompiled script was corrupt, written by an incompatible version,
+    /// or could not be run on this backend.
+    Bytecode(String),
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoxError::Io(err) => write!(f, "{}", err),
+            LoxError::Compile(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                write!(f, "{}", messages.join("\n"))
+            }
+            LoxError::Runtime(err) => write!(f, "{}", err),
+            LoxError::Bytecode(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl error::Error for LoxError {}
+
+impl From<io::Error> for LoxError {
+    fn from(err: io::Error) -> Self {
+        LoxError::Io(err)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(err: RuntimeError) -> Self {
+        LoxError::Runtime(err)
+    }
+}
@@ -0,0 +1,82 @@
+use crate::environment::Environment;
+use crate::expression::FunctionDecl;
+use crate::interpreter::{Interpreter, LoxValue, Unwind};
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_instance::LoxInstance;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+pub struct LoxFunction {
+    declaration: Rc<FunctionDecl>,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<FunctionDecl>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> LoxFunction {
+        LoxFunction {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    pub fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let mut environment = Environment::new_enclosed(self.closure.clone());
+        environment.define("this", LoxValue::Instance(instance));
+        LoxFunction::new(
+            self.declaration.clone(),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+
+    fn this(&self) -> LoxValue {
+        self.closure
+            .borrow()
+            .get_at(0, "this")
+            .expect("initializers are always bound to an instance")
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn name(&self) -> &str {
+        &self.declaration.name.lexeme
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::Fixed(self.declaration.params.len())
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        _paren: &Token,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, RuntimeError> {
+        let mut environment = Environment::new_enclosed(self.closure.clone());
+        for (param, argument) in self.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, argument);
+        }
+
+        match interpreter.execute_block(&self.declaration.body, environment) {
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => Ok(self.this()),
+            Ok(()) => Ok(LoxValue::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(err)) => Err(err),
+        }
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fn {}>", self.declaration.name.lexeme)
+    }
+}
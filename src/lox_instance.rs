@@ -0,0 +1,68 @@
+use crate::error_code::ErrorCode;
+use crate::interpreter::LoxValue;
+use crate::lox_class::LoxClass;
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<String, LoxValue>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> LoxInstance {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn get(
+        instance: &Rc<RefCell<LoxInstance>>,
+        name: &Token,
+    ) -> Result<LoxValue, RuntimeError> {
+        LoxInstance::property(instance, &name.lexeme).ok_or_else(|| {
+            RuntimeError::new(
+                name.clone(),
+                ErrorCode::UndefinedProperty,
+                &format!("Undefined property '{}'.", name.lexeme),
+            )
+        })
+    }
+
+    /// Looks up a field, falling back to a method bound to `instance`.
+    pub fn property(instance: &Rc<RefCell<LoxInstance>>, name: &str) -> Option<LoxValue> {
+        let method = {
+            let this = instance.borrow();
+            if let Some(value) = this.fields.get(name) {
+                return Some(value.clone());
+            }
+
+            this.class.find_method(name)?
+        };
+
+        Some(method.bind(instance.clone()))
+    }
+
+    pub fn field(&self, name: &str) -> Option<LoxValue> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set_field(&mut self, name: &str, value: LoxValue) {
+        self.fields.insert(name.to_string(), value);
+    }
+
+    pub fn set(&mut self, name: &Token, value: LoxValue) {
+        self.fields.insert(name.lexeme.clone(), value);
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
@@ -1,122 +1,247 @@
-mod expression;
-mod interpreter;
-mod parser;
-mod runtime_error;
-mod scanner;
-mod token;
-
-use crate::expression::AstPrinter;
-use crate::interpreter::Interpreter;
-use crate::runtime_error::RuntimeError;
-use crate::token::{Token, TokenType};
-use parser::Parser;
-use scanner::Scanner;
-use std::fs::exists;
-use std::io::{Write, stdout};
+use lox::{Backend, ErrorCode, ErrorFormat, Lox, LoxError, Value};
+use std::io::Write;
+use std::path::Path;
 use std::process::exit;
-use std::{env, fs, io};
+use std::{env, fs, io, panic, thread};
+
+const USAGE: &str = "Usage: lox [--error-format=human|json] [--backend=tree|vm] [--no-warnings] [--trace-execution] [--dump-bytecode] [script]
+       lox disasm <script>
+       lox compile <script> [-o <output>]
+       lox run <compiled script>
+       lox --explain E0xxx";
+
+#[derive(PartialEq)]
+enum Command {
+    /// Runs a script, or starts the prompt without one.
+    Interpret,
+    Disasm,
+    Compile,
+    /// Runs a script precompiled by `compile`.
+    Run,
+}
 
-struct Lox {
-    had_error: bool,
-    had_runtime_error: bool,
+struct Options {
+    command: Command,
+    error_format: ErrorFormat,
+    backend: Backend,
+    warnings: bool,
+    trace_execution: bool,
+    dump_bytecode: bool,
+    explain: Option<String>,
+    script: Option<String>,
+    output: Option<String>,
 }
 
-impl Lox {
-    fn new() -> Lox {
-        Lox {
-            had_error: false,
-            had_runtime_error: false,
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        command: Command::Interpret,
+        error_format: ErrorFormat::Human,
+        backend: Backend::TreeWalk,
+        warnings: true,
+        trace_execution: false,
+        dump_bytecode: false,
+        explain: None,
+        script: None,
+        output: None,
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "--explain" {
+            match args.next() {
+                Some(code) => options.explain = Some(code),
+                None => return Err(String::from("Expected an error code after '--explain'.")),
+            }
+        } else if let Some(format) = arg.strip_prefix("--error-format=") {
+            options.error_format = match format {
+                "human" => ErrorFormat::Human,
+                "json" => ErrorFormat::Json,
+                _ => return Err(format!("Unknown error format '{}'.", format)),
+            };
+        } else if let Some(backend) = arg.strip_prefix("--backend=") {
+            options.backend = match backend {
+                "tree" => Backend::TreeWalk,
+                "vm" => Backend::Vm,
+                _ => return Err(format!("Unknown backend '{}'.", backend)),
+            };
+        } else if arg == "--no-warnings" {
+            options.warnings = false;
+        } else if arg == "--trace-execution" {
+            options.trace_execution = true;
+        } else if arg == "--dump-bytecode" {
+            options.dump_bytecode = true;
+        } else if arg == "-o" {
+            match args.next() {
+                Some(output) => options.output = Some(output),
+                None => return Err(String::from("Expected a file name after '-o'.")),
+            }
+        } else if arg.starts_with("-") {
+            return Err(format!("Unknown option '{}'.", arg));
+        } else if options.command == Command::Interpret
+            && options.script.is_none()
+            && let Some(command) = match arg.as_str() {
+                "disasm" => Some(Command::Disasm),
+                "compile" => Some(Command::Compile),
+                "run" => Some(Command::Run),
+                _ => None,
+            }
+        {
+            options.command = command;
+        } else if options.script.is_none() {
+            options.script = Some(arg);
+        } else {
+            return Err(String::from("Expected at most one script."));
         }
     }
 
-    fn run(&mut self, line: String) {
-        print!("running line: {}", line);
-        let mut scanner = Scanner::new(line, self);
-        let tokens = scanner.scan_tokens();
-        println!("tokens are: {:?}", tokens);
-
-        let mut parser = Parser::new(tokens, self);
-        let expression = parser.parse();
-        if self.had_error {
-            return;
-        }
-
-        let mut interpreter = Interpreter::new(self);
-        // println!("{}", AstPrinter.print(&expression.unwrap()));
-
-        interpreter.interpret(&expression.unwrap());
+    if options.command != Command::Interpret && options.script.is_none() {
+        return Err(String::from("Expected a script."));
+    }
+    if options.output.is_some() && options.command != Command::Compile {
+        return Err(String::from("'-o' only applies to 'lox compile'."));
     }
 
-    fn error_lexer(&mut self, line: usize, message: &str) {
-        self.report(line, "", message);
+    // The debugging aids and precompiled scripts only exist for bytecode.
+    if options.trace_execution || options.dump_bytecode || options.command == Command::Run {
+        options.backend = Backend::Vm;
     }
 
-    fn error_parser(&mut self, token: &Token, message: &str) {
-        if token.token_type == TokenType::Eof {
-            self.report(token.line, " at end", message);
-        } else {
-            let where_in_cord = format!("at '{}'", token.lexeme);
-            self.report(token.line, &where_in_cord.as_str(), message);
+    Ok(options)
+}
+
+fn explain(code: &str) {
+    match ErrorCode::from_code(code) {
+        Some(code) => print!("{}", code.explanation()),
+        None => {
+            eprintln!("No explanation for error code '{}'.", code);
+            exit(64)
         }
     }
+}
 
-    fn error_runtime(&mut self, err: RuntimeError) {
-        println!("{}\n[line {}]", err.message, err.token.line);
-        self.had_runtime_error = true;
+fn disassemble(lox: &mut Lox, file_path: String) {
+    match lox.disassemble_file(file_path) {
+        Ok(listing) => print!("{}", listing),
+        Err(LoxError::Io(err)) => {
+            eprintln!("Could not read file: {}", err);
+            exit(74)
+        }
+        Err(_) => exit(65),
     }
+}
 
-    fn report(&mut self, line: usize, where_in_code: &str, message: &str) {
-        println!("[line {}] Error {}: {}", line, where_in_code, message);
-        self.had_error = true;
+fn compile(lox: &mut Lox, file_path: String, output: Option<String>) {
+    let output = output.unwrap_or_else(|| {
+        let path = Path::new(&file_path).with_extension("loxc");
+        path.display().to_string()
+    });
+
+    match lox.compile_file(&file_path) {
+        Ok(bytes) => {
+            if let Err(err) = fs::write(&output, bytes) {
+                eprintln!("Could not write '{}': {}", output, err);
+                exit(74)
+            }
+        }
+        Err(LoxError::Io(err)) => {
+            eprintln!("Could not read file: {}", err);
+            exit(74)
+        }
+        Err(_) => exit(65),
     }
+}
 
-    fn run_file(&mut self, file_path: String) {
-        println!("Running file: {}", file_path);
-        let content = fs::read_to_string(file_path).expect("Could not read file");
-        self.run(content);
-        if self.had_error {
+fn run_compiled(lox: &mut Lox, file_path: String) {
+    match lox.run_bytecode_file(&file_path) {
+        Ok(_) => {}
+        Err(LoxError::Io(err)) => {
+            eprintln!("Could not read file: {}", err);
+            exit(74)
+        }
+        Err(LoxError::Bytecode(message)) => {
+            eprintln!("Could not load '{}': {}", file_path, message);
             exit(65)
         }
+        Err(LoxError::Compile(_)) => exit(65),
+        Err(LoxError::Runtime(_)) => exit(70),
+    }
+}
 
-        if self.had_runtime_error {
-            exit(70)
+fn run_file(lox: &mut Lox, file_path: String) {
+    // Diagnostics have already been written to stderr by the time an error
+    // comes back, so only the exit code is left to decide.
+    match lox.run_file(file_path) {
+        Ok(_) => {}
+        Err(LoxError::Io(err)) => {
+            eprintln!("Could not read file: {}", err);
+            exit(74)
         }
+        Err(LoxError::Compile(_) | LoxError::Bytecode(_)) => exit(65),
+        Err(LoxError::Runtime(_)) => exit(70),
     }
+}
 
-    fn run_prompt(&mut self) {
-        println!("Welcome to lox!");
-        loop {
-            print!("> ");
-            io::stdout().flush().unwrap();
-
-            let stdin = io::stdin();
-            let mut buffer = String::new();
-            match stdin.read_line(&mut buffer) {
-                Ok(n) => {
-                    if n == 0 {
-                        break;
-                    }
-                    self.run(buffer);
-                    self.had_error = false;
-                }
-                Err(error) => {
-                    println!("error: {}", error);
+fn run_prompt(lox: &mut Lox) {
+    println!("Welcome to lox!");
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+
+        let stdin = io::stdin();
+        let mut buffer = String::new();
+        match stdin.read_line(&mut buffer) {
+            Ok(n) => {
+                if n == 0 {
                     break;
                 }
+                if let Ok(value) = lox.eval(&buffer)
+                    && value != Value::Nil
+                {
+                    println!("{}", value);
+                }
+            }
+            Err(error) => {
+                eprintln!("error: {}", error);
+                break;
             }
         }
     }
 }
 
 fn main() {
-    let mut args = env::args();
-    let mut lox = Lox::new();
-
-    if args.len() > 2 {
-        println!("Usage: jlox [script]");
-    } else if args.len() == 2 {
-        lox.run_file(args.nth(1).unwrap());
-    } else {
-        lox.run_prompt();
+    // Every call the tree-walker makes nests the host's stack.
+    let interpreter = thread::Builder::new()
+        .stack_size(lox::STACK_SIZE)
+        .spawn(interpret)
+        .expect("could not start the interpreter thread");
+    if let Err(panic) = interpreter.join() {
+        panic::resume_unwind(panic);
+    }
+}
+
+fn interpret() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n{}", message, USAGE);
+            exit(64)
+        }
+    };
+
+    if let Some(code) = options.explain {
+        return explain(&code);
+    }
+
+    let mut lox = Lox::with_backend(options.backend);
+    lox.set_error_format(options.error_format);
+    lox.set_warnings(options.warnings);
+    lox.set_trace_execution(options.trace_execution);
+    lox.set_dump_bytecode(options.dump_bytecode);
+
+    match (options.command, options.script) {
+        (Command::Disasm, Some(script)) => disassemble(&mut lox, script),
+        (Command::Compile, Some(script)) => compile(&mut lox, script, options.output),
+        (Command::Run, Some(script)) => run_compiled(&mut lox, script),
+        (_, Some(script)) => run_file(&mut lox, script),
+        (_, None) => run_prompt(&mut lox),
     }
 }
@@ -0,0 +1,72 @@
+use crate::interpreter::{Interpreter, LoxValue};
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::runtime_error::RuntimeError;
+use crate::token::Token;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type NativeFn = dyn Fn(&[LoxValue]) -> Result<LoxValue, RuntimeError>;
+
+/// A function implemented by the host in Rust.
+pub struct NativeFunction {
+    name: String,
+    arity: Arity,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(name: &str, arity: Arity, function: Box<NativeFn>) -> NativeFunction {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            function,
+        }
+    }
+
+    /// Runs the function, moving any error it raises to `call_site` since
+    /// natives have no source location of their own.
+    pub fn invoke(
+        &self,
+        call_site: &Token,
+        arguments: &[LoxValue],
+    ) -> Result<LoxValue, RuntimeError> {
+        (self.function)(arguments)
+            .map_err(|err| RuntimeError::new(call_site.clone(), err.code, &err.message))
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn call(
+        &self,
+        _interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: Vec<LoxValue>,
+    ) -> Result<LoxValue, RuntimeError> {
+        self.invoke(paren, &arguments)
+    }
+
+    fn as_native(&self) -> Option<&NativeFunction> {
+        Some(self)
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+pub fn clock(_arguments: &[LoxValue]) -> Result<LoxValue, RuntimeError> {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| RuntimeError::native("System clock is set before the Unix epoch."))?;
+    Ok(LoxValue::Number(elapsed.as_secs_f64()))
+}
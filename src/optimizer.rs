@@ -0,0 +1,516 @@
+use crate::Lox;
+use crate::diagnostic::Diagnostic;
+use crate::error_code::ErrorCode;
+use crate::expression::{Expr, FunctionDecl, LiteralValue, Stmt, StmtVisitor, Visitor};
+use crate::interpreter::LoxValue;
+use crate::token::{Token, TokenType};
+use std::rc::Rc;
+
+/// Rewrites the AST before execution, evaluating operators whose operands
+/// are all literals and dropping the `Grouping` nodes that only mattered to
+/// the parser.
+///
+/// A fold is only made when evaluating it cannot fail, so `"a" - 1` is left
+/// for the runtime to report at its own token. Identities such as `x * 1`
+/// are not applied for the same reason: `x` might not be a number.
+///
+/// Code that can never run is removed as well: statements after a `return`
+/// or `throw` in the same block, and the branches and loops whose condition
+/// folded to a constant. Each removal is reported as a warning at the first
+/// statement dropped.
+pub struct Optimizer<'a> {
+    lox: &'a mut Lox,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(lox: &'a mut Lox) -> Optimizer<'a> {
+        Optimizer { lox }
+    }
+
+    pub fn optimize(&mut self, statements: &[Stmt]) -> Vec<Stmt> {
+        let mut optimized = Vec::new();
+        for (i, statement) in statements.iter().enumerate() {
+            optimized.push(statement.accept(self));
+
+            if let Stmt::Return(keyword, _) | Stmt::Throw(keyword, _) = statement {
+                let rest = &statements[i + 1..];
+                if !rest.is_empty() {
+                    let reason = format!("any code after this '{}' never runs", keyword.lexeme);
+                    self.unreachable(rest, keyword, &reason);
+                }
+                break;
+            }
+        }
+        optimized
+    }
+
+    fn unreachable(&mut self, dead: &[Stmt], cause: &Token, reason: &str) {
+        let diagnostic = match dead.iter().find_map(first_token) {
+            Some(token) => Diagnostic::warning("Unreachable code.", token.span, token.line)
+                .with_label(cause.span, reason),
+            None => {
+                Diagnostic::warning("Unreachable code.", cause.span, cause.line).with_note(reason)
+            }
+        };
+        self.lox
+            .report(diagnostic.with_code(ErrorCode::UnreachableCode));
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Expr {
+        expr.accept(self)
+    }
+
+    fn boxed(&mut self, expr: &Expr) -> Box<Expr> {
+        Box::new(self.expr(expr))
+    }
+
+    fn function(&mut self, declaration: &FunctionDecl) -> Rc<FunctionDecl> {
+        Rc::new(FunctionDecl {
+            name: declaration.name.clone(),
+            params: declaration.params.clone(),
+            body: self.optimize(&declaration.body),
+        })
+    }
+}
+
+/// The first token of `stmt` that the AST kept, if any. Literals carry no
+/// token, so `1;` has none.
+fn first_token(stmt: &Stmt) -> Option<&Token> {
+    match stmt {
+        Stmt::Block(statements) => statements.iter().find_map(first_token),
+        Stmt::Class(name, ..) | Stmt::Var(name, _) => Some(name),
+        Stmt::Expression(expr) => expr_token(expr),
+        Stmt::Function(declaration) => Some(&declaration.name),
+        Stmt::If(keyword, ..)
+        | Stmt::Print(keyword, _)
+        | Stmt::Return(keyword, _)
+        | Stmt::Throw(keyword, _)
+        | Stmt::While(keyword, ..) => Some(keyword),
+        Stmt::Try(body, catch, finally) => body
+            .iter()
+            .find_map(first_token)
+            .or(catch.as_ref().map(|(name, _)| name))
+            .or_else(|| finally.iter().flatten().find_map(first_token)),
+    }
+}
+
+fn expr_token(expr: &Expr) -> Option<&Token> {
+    match expr {
+        Expr::Assign(_, name, _) | Expr::Variable(_, name) => Some(name),
+        Expr::Binary(left, operator, _) | Expr::Logical(left, operator, _) => {
+            expr_token(left).or(Some(operator))
+        }
+        Expr::Call(callee, paren, _) => expr_token(callee).or(Some(paren)),
+        Expr::Get(object, name) | Expr::Set(object, name, _) => expr_token(object).or(Some(name)),
+        Expr::Grouping(expression) => expr_token(expression),
+        Expr::Interpolation(parts) => parts.iter().find_map(expr_token),
+        Expr::Literal(_) => None,
+        Expr::Super(_, keyword, _) | Expr::This(_, keyword) => Some(keyword),
+        Expr::Unary(operator, _) => Some(operator),
+    }
+}
+
+fn value(literal: &LiteralValue) -> LoxValue {
+    match literal {
+        LiteralValue::Number(n) => LoxValue::Number(*n),
+        LiteralValue::String(s) => LoxValue::String(s.clone()),
+        LiteralValue::True => LoxValue::Boolean(true),
+        LiteralValue::False => LoxValue::Boolean(false),
+        LiteralValue::Nil => LoxValue::Nil,
+    }
+}
+
+fn literal(value: LoxValue) -> Expr {
+    Expr::Literal(match value {
+        LoxValue::Number(n) => LiteralValue::Number(n),
+        LoxValue::String(s) => LiteralValue::String(s),
+        LoxValue::Boolean(true) => LiteralValue::True,
+        LoxValue::Boolean(false) => LiteralValue::False,
+        LoxValue::Nil => LiteralValue::Nil,
+        _ => unreachable!("operators on literals only produce literals"),
+    })
+}
+
+/// Evaluates `left operator right` the way the interpreter would, or returns
+/// `None` if that would raise an error.
+fn fold_binary(left: &LiteralValue, operator: &Token, right: &LiteralValue) -> Option<LoxValue> {
+    let (left, right) = (value(left), value(right));
+    let numbers = matches!((&left, &right), (LoxValue::Number(_), LoxValue::Number(_)));
+
+    let result = match operator.token_type {
+        TokenType::EqualEqual => LoxValue::Boolean(left == right),
+        TokenType::BangEqual => LoxValue::Boolean(left != right),
+        TokenType::Plus => match (left, right) {
+            (LoxValue::String(left), LoxValue::String(right)) => LoxValue::String(left + &right),
+            (left, right) if numbers => left + right,
+            _ => return None,
+        },
+        _ if !numbers => return None,
+        TokenType::Minus => left - right,
+        TokenType::Star => left * right,
+        TokenType::Slash => left / right,
+        TokenType::Greater => LoxValue::Boolean(left > right),
+        TokenType::GreaterEqual => LoxValue::Boolean(left >= right),
+        TokenType::Less => LoxValue::Boolean(left < right),
+        TokenType::LessEqual => LoxValue::Boolean(left <= right),
+        _ => return None,
+    };
+    Some(result)
+}
+
+impl Visitor<Expr> for Optimizer<'_> {
+    fn visit_assign_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Assign(depth, name, value) = expr else {
+            unreachable!()
+        };
+
+        Expr::Assign(depth.clone(), name.clone(), self.boxed(value))
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Binary(left, operator, right) = expr else {
+            unreachable!()
+        };
+
+        let (left, right) = (self.expr(left), self.expr(right));
+        if let (Expr::Literal(l), Expr::Literal(r)) = (&left, &right)
+            && let Some(result) = fold_binary(l, operator, r)
+        {
+            return literal(result);
+        }
+
+        Expr::Binary(Box::new(left), operator.clone(), Box::new(right))
+    }
+
+    fn visit_call_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Call(callee, paren, arguments) = expr else {
+            unreachable!()
+        };
+
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.expr(argument))
+            .collect();
+        Expr::Call(self.boxed(callee), paren.clone(), arguments)
+    }
+
+    fn visit_get_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Get(object, name) = expr else {
+            unreachable!()
+        };
+
+        Expr::Get(self.boxed(object), name.clone())
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Grouping(expression) = expr else {
+            unreachable!()
+        };
+
+        self.expr(expression)
+    }
+
+    fn visit_interpolation_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Interpolation(parts) = expr else {
+            unreachable!()
+        };
+
+        let parts: Vec<Expr> = parts.iter().map(|part| self.expr(part)).collect();
+        let mut string = String::new();
+        for part in &parts {
+            let Expr::Literal(part) = part else {
+                return Expr::Interpolation(parts);
+            };
+            string.push_str(&value(part).to_string());
+        }
+        Expr::Literal(LiteralValue::String(string))
+    }
+
+    fn visit_literal_expr(&mut self, expr: &Expr) -> Expr {
+        expr.clone()
+    }
+
+    fn visit_logical_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Logical(left, operator, right) = expr else {
+            unreachable!()
+        };
+
+        let left = self.expr(left);
+        let Expr::Literal(value) = &left else {
+            return Expr::Logical(Box::new(left), operator.clone(), self.boxed(right));
+        };
+
+        // A literal left operand decides on its own which side is the result.
+        let truthy = self::value(value).is_truthy();
+        if truthy == (operator.token_type == TokenType::Or) {
+            left
+        } else {
+            self.expr(right)
+        }
+    }
+
+    fn visit_set_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Set(object, name, value) = expr else {
+            unreachable!()
+        };
+
+        Expr::Set(self.boxed(object), name.clone(), self.boxed(value))
+    }
+
+    fn visit_super_expr(&mut self, expr: &Expr) -> Expr {
+        expr.clone()
+    }
+
+    fn visit_this_expr(&mut self, expr: &Expr) -> Expr {
+        expr.clone()
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Expr) -> Expr {
+        let Expr::Unary(operator, right) = expr else {
+            unreachable!()
+        };
+
+        let right = self.expr(right);
+        match (&operator.token_type, &right) {
+            (TokenType::Minus, Expr::Literal(LiteralValue::Number(n))) => {
+                Expr::Literal(LiteralValue::Number(-n))
+            }
+            (TokenType::Bang, Expr::Literal(value)) => literal(!self::value(value)),
+            _ => Expr::Unary(operator.clone(), Box::new(right)),
+        }
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Expr) -> Expr {
+        expr.clone()
+    }
+}
+
+impl StmtVisitor<Stmt> for Optimizer<'_> {
+    fn visit_block_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Block(statements) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Block(self.optimize(statements))
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Class(name, superclass, methods) = stmt else {
+            unreachable!()
+        };
+
+        let methods = methods.iter().map(|method| self.function(method)).collect();
+        Stmt::Class(name.clone(), superclass.clone(), methods)
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Expression(expression) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Expression(self.expr(expression))
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Function(declaration) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Function(self.function(declaration))
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::If(keyword, condition, then_branch, else_branch) = stmt else {
+            unreachable!()
+        };
+
+        let condition = self.expr(condition);
+        let Expr::Literal(value) = &condition else {
+            return Stmt::If(
+                keyword.clone(),
+                condition,
+                Box::new(then_branch.accept(self)),
+                else_branch
+                    .as_ref()
+                    .map(|else_branch| Box::new(else_branch.accept(self))),
+            );
+        };
+
+        // The test itself stays so that a trailing `if` still leaves `nil` as
+        // the value of a script; only the branch that cannot run goes.
+        let truthy = self::value(value).is_truthy();
+        let (live, dead) = if truthy {
+            (Some(then_branch), else_branch.as_ref())
+        } else {
+            (else_branch.as_ref(), Some(then_branch))
+        };
+        if let Some(dead) = dead {
+            let reason = format!("this condition is always {}", truthy);
+            self.unreachable(std::slice::from_ref(dead), keyword, &reason);
+        }
+
+        match live {
+            Some(live) if truthy => Stmt::If(
+                keyword.clone(),
+                condition,
+                Box::new(live.accept(self)),
+                None,
+            ),
+            Some(live) => Stmt::If(
+                keyword.clone(),
+                condition,
+                Box::new(Stmt::Block(Vec::new())),
+                Some(Box::new(live.accept(self))),
+            ),
+            None => Stmt::Block(Vec::new()),
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Print(keyword, expression) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Print(keyword.clone(), self.expr(expression))
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Return(keyword, value) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Return(
+            keyword.clone(),
+            value.as_ref().map(|value| self.expr(value)),
+        )
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Throw(keyword, value) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Throw(keyword.clone(), self.expr(value))
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Try(body, catch, finally) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Try(
+            self.optimize(body),
+            catch
+                .as_ref()
+                .map(|(name, body)| (name.clone(), self.optimize(body))),
+            finally.as_ref().map(|finally| self.optimize(finally)),
+        )
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::Var(name, initializer) = stmt else {
+            unreachable!()
+        };
+
+        Stmt::Var(
+            name.clone(),
+            initializer.as_ref().map(|value| self.expr(value)),
+        )
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        let Stmt::While(keyword, condition, body) = stmt else {
+            unreachable!()
+        };
+
+        let condition = self.expr(condition);
+        if let Expr::Literal(value) = &condition
+            && !self::value(value).is_truthy()
+        {
+            self.unreachable(
+                std::slice::from_ref(body),
+                keyword,
+                "this condition is always false",
+            );
+            return Stmt::Block(Vec::new());
+        }
+
+        Stmt::While(keyword.clone(), condition, Box::new(body.accept(self)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lox;
+    use crate::expression::AstPrinter;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn optimize(source: &str) -> Vec<String> {
+        let mut lox = Lox::new();
+        let statements = Parser::new(Scanner::new(source), &mut lox).parse();
+        Optimizer::new(&mut lox)
+            .optimize(&statements)
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Expression(expr) => AstPrinter.print(expr),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_folds_constant_subtrees() {
+        let folded = optimize(
+            "(1 + 2) * 3; !true; \"a\" + \"b\"; -(4); 1 / 0; nil == false; 2 >= 2;\
+             true or x; nil and x; \"${1}-${\"x\" + \"y\"}\"; (x) + (2 * 3);",
+        );
+        assert_eq!(
+            folded,
+            vec![
+                "9", "false", "\"ab\"", "-4", "nil", "false", "true", "true", "nil", "\"1-xy\"",
+                "(+ x 6)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leaves_folds_that_would_fail_to_the_runtime() {
+        let folded = optimize("\"a\" - 1; -\"b\"; \"a\" < \"b\"; 1 + nil; (\"a\" - 1) + 2;");
+        assert_eq!(
+            folded,
+            vec![
+                "(- \"a\" 1)",
+                "(- \"b\")",
+                "(< \"a\" \"b\")",
+                "(+ 1 nil)",
+                "(+ (- \"a\" 1) 2)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_removes_code_that_can_never_run() {
+        let mut lox = Lox::new();
+        lox.set_error_output(std::io::sink());
+        let source = "fun f() { return; print 1; print 2; }\n\
+                      if (!true) print 3; else print 4;\nwhile (false) print 5;";
+        let statements = Parser::new(Scanner::new(source), &mut lox).parse();
+        let statements = Optimizer::new(&mut lox).optimize(&statements);
+
+        let Stmt::Function(f) = &statements[0] else {
+            panic!("expected a function");
+        };
+        assert!(matches!(f.body.as_slice(), [Stmt::Return(..)]));
+        let Stmt::If(_, _, then_branch, Some(_)) = &statements[1] else {
+            panic!("expected the else branch to remain");
+        };
+        assert!(matches!(**then_branch, Stmt::Block(ref block) if block.is_empty()));
+        assert!(matches!(&statements[2], Stmt::Block(block) if block.is_empty()));
+
+        let lines: Vec<_> = lox.errors.iter().map(|warning| warning.line).collect();
+        assert_eq!(lines, vec![1, 2, 3]);
+        assert!(!lox.had_error);
+    }
+}
@@ -1,35 +1,84 @@
 use crate::Lox;
-use crate::expression::{Expr, LiteralValue};
-use crate::token::{Literal, Token, TokenType};
-
-pub struct Parser<'a> {
-    tokens: Vec<Token>,
-    current: usize,
+use crate::diagnostic::Diagnostic;
+use crate::error_code::ErrorCode;
+use crate::expression::{Depth, Expr, FunctionDecl, LiteralValue, Stmt};
+use crate::scanner::LexError;
+use crate::syntax::{SyntaxBuilder, SyntaxKind, SyntaxNode};
+use crate::token::{Literal, Span, StringPart, Token, TokenType};
+use std::rc::Rc;
+
+/// Marker for a syntax error that has already been reported; it only
+/// unwinds the parser back to the nearest statement boundary.
+#[derive(Debug)]
+pub struct ParseError;
+
+/// Pulls tokens from the scanner one at a time, keeping only the current
+/// token as lookahead and the one before it.
+pub struct Parser<'a, I: Iterator<Item = Result<Token, LexError>>> {
+    tokens: I,
+    current: Token,
+    previous: Token,
+    /// Collects every consumed token into a concrete syntax tree when
+    /// parsing losslessly.
+    syntax: Option<SyntaxBuilder>,
     lox: &'a mut Lox,
 }
 
-impl<'a> Parser<'a> {
-    pub fn new(tokens: Vec<Token>, lox: &'a mut Lox) -> Parser<'a> {
-        Parser {
+impl<'a, I: Iterator<Item = Result<Token, LexError>>> Parser<'a, I> {
+    pub fn new(tokens: I, lox: &'a mut Lox) -> Parser<'a, I> {
+        let eof = Token::new(TokenType::Eof, String::new(), None, 1, 1, Span::default());
+        let mut parser = Parser {
             tokens,
-            current: 0,
+            current: eof.clone(),
+            previous: eof,
+            syntax: None,
             lox,
+        };
+        parser.current = parser.next_token();
+        parser
+    }
+
+    /// Parses the whole token stream, recovering after each syntax error so
+    /// that every error is reported through `lox`. Statements that failed to
+    /// parse are left out of the returned AST.
+    pub fn parse(mut self) -> Vec<Stmt> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
         }
+
+        statements
     }
 
-    pub fn parse(&mut self) -> Result<Expr, String> {
-        self.expression()
+    /// Parses like [`Parser::parse`], also building a syntax tree that holds
+    /// every token and its trivia, including the tokens skipped while
+    /// recovering from errors.
+    pub fn parse_lossless(mut self) -> (SyntaxNode, Vec<Stmt>) {
+        self.syntax = Some(SyntaxBuilder::default());
+
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        let mut syntax = self.syntax.take().unwrap();
+        syntax.token(self.current.clone());
+        (syntax.finish(), statements)
     }
 
     fn synchronize(&mut self) {
         self.advance();
 
-        while (!self.is_at_end()) {
+        while !self.is_at_end() {
             if self.previous().token_type == TokenType::Semicolon {
                 return;
             }
 
-            if vec![
+            if [
                 TokenType::Class,
                 TokenType::Fun,
                 TokenType::Var,
@@ -38,6 +87,8 @@ impl<'a> Parser<'a> {
                 TokenType::While,
                 TokenType::Print,
                 TokenType::Return,
+                TokenType::Throw,
+                TokenType::Try,
             ]
             .contains(&self.peek().token_type)
             {
@@ -47,23 +98,364 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
-        self.equality()
+    fn declaration(&mut self) -> Option<Stmt> {
+        let checkpoint = self.checkpoint();
+        let result = if self.matches_any(&[&TokenType::Class]) {
+            let class = self.class_declaration();
+            self.node(checkpoint, SyntaxKind::ClassDecl, class)
+        } else if self.matches_any(&[&TokenType::Fun]) {
+            let function = self.function("function").map(Stmt::Function);
+            self.node(checkpoint, SyntaxKind::FunDecl, function)
+        } else if self.matches_any(&[&TokenType::Var]) {
+            let var = self.var_declaration();
+            self.node(checkpoint, SyntaxKind::VarDecl, var)
+        } else {
+            self.statement()
+        };
+
+        match result {
+            Ok(statement) => Some(statement),
+            Err(ParseError) => {
+                self.synchronize();
+                self.wrap(checkpoint, SyntaxKind::Error);
+                None
+            }
+        }
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume_identifier("Expect class name.")?;
+
+        let mut superclass = None;
+        if self.matches_any(&[&TokenType::Less]) {
+            let checkpoint = self.checkpoint();
+            let superclass_name = self.consume_identifier("Expect superclass name.")?;
+            self.wrap(checkpoint, SyntaxKind::VariableExpr);
+            superclass = Some(Expr::Variable(Depth::default(), superclass_name));
+        }
+
+        self.consume(&TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            let checkpoint = self.checkpoint();
+            let method = self.function("method");
+            methods.push(self.node(checkpoint, SyntaxKind::FunDecl, method)?);
+        }
+
+        self.consume(&TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(Stmt::Class(name, superclass, methods))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Rc<FunctionDecl>, ParseError> {
+        let name = self.consume_identifier(&format!("Expect {} name.", kind))?;
+        let checkpoint = self.checkpoint();
+        self.consume(
+            &TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+
+        let mut params = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if params.len() >= 255 {
+                    self.error(
+                        &self.peek(),
+                        ErrorCode::TooManyArguments,
+                        "Can't have more than 255 parameters.",
+                    );
+                }
+
+                params.push(self.consume_identifier("Expect parameter name.")?);
+                if !self.matches_any(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.wrap(checkpoint, SyntaxKind::ParamList);
+
+        let checkpoint = self.checkpoint();
+        self.consume(
+            &TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.block()?;
+        self.wrap(checkpoint, SyntaxKind::Block);
+
+        Ok(Rc::new(FunctionDecl { name, params, body }))
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume_identifier("Expect variable name.")?;
+
+        let mut initializer = None;
+        if self.matches_any(&[&TokenType::Equal]) {
+            initializer = Some(self.expression()?);
+        }
+
+        self.consume(
+            &TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(Stmt::Var(name, initializer))
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        let checkpoint = self.checkpoint();
+        let (kind, statement) = if self.matches_any(&[&TokenType::For]) {
+            (SyntaxKind::ForStmt, self.for_statement())
+        } else if self.matches_any(&[&TokenType::If]) {
+            (SyntaxKind::IfStmt, self.if_statement())
+        } else if self.matches_any(&[&TokenType::Print]) {
+            (SyntaxKind::PrintStmt, self.print_statement())
+        } else if self.matches_any(&[&TokenType::Return]) {
+            (SyntaxKind::ReturnStmt, self.return_statement())
+        } else if self.matches_any(&[&TokenType::Throw]) {
+            (SyntaxKind::ThrowStmt, self.throw_statement())
+        } else if self.matches_any(&[&TokenType::Try]) {
+            (SyntaxKind::TryStmt, self.try_statement())
+        } else if self.matches_any(&[&TokenType::While]) {
+            (SyntaxKind::WhileStmt, self.while_statement())
+        } else if self.matches_any(&[&TokenType::LeftBrace]) {
+            (SyntaxKind::Block, self.block().map(Stmt::Block))
+        } else {
+            (SyntaxKind::ExprStmt, self.expression_statement())
+        };
+
+        self.node(checkpoint, kind, statement)
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
+
+        let checkpoint = self.checkpoint();
+        let initializer = if self.matches_any(&[&TokenType::Semicolon]) {
+            None
+        } else if self.matches_any(&[&TokenType::Var]) {
+            let var = self.var_declaration();
+            Some(self.node(checkpoint, SyntaxKind::VarDecl, var)?)
+        } else {
+            let expression = self.expression_statement();
+            Some(self.node(checkpoint, SyntaxKind::ExprStmt, expression)?)
+        };
+
+        let condition = if self.check(&TokenType::Semicolon) {
+            Expr::Literal(LiteralValue::True)
+        } else {
+            self.expression()?
+        };
+        self.consume(&TokenType::Semicolon, "Expect ';' after loop condition.")?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(&TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        // Desugar into `{ initializer; while (condition) { body; increment; } }`.
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+
+        body = Stmt::While(keyword, condition, Box::new(body));
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let mut else_branch = None;
+        if self.matches_any(&[&TokenType::Else]) {
+            else_branch = Some(Box::new(self.statement()?));
+        }
+
+        Ok(Stmt::If(keyword, condition, then_branch, else_branch))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.statement()?;
+
+        Ok(Stmt::While(keyword, condition, Box::new(body)))
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+        }
+
+        self.consume(&TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(Stmt::Print(keyword, value))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let mut value = None;
+        if !self.check(&TokenType::Semicolon) {
+            value = Some(self.expression()?);
+        }
+
+        self.consume(&TokenType::Semicolon, "Expect ';' after return value.")?;
+        Ok(Stmt::Return(keyword, value))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after thrown value.")?;
+        Ok(Stmt::Throw(keyword, value))
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous();
+        let checkpoint = self.checkpoint();
+        self.consume(&TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+        self.wrap(checkpoint, SyntaxKind::Block);
+
+        let mut catch = None;
+        let checkpoint = self.checkpoint();
+        if self.matches_any(&[&TokenType::Catch]) {
+            self.consume(&TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self.consume_identifier("Expect error variable name.")?;
+            self.consume(&TokenType::RightParen, "Expect ')' after error variable.")?;
+            let block = self.checkpoint();
+            self.consume(&TokenType::LeftBrace, "Expect '{' after catch clause.")?;
+            catch = Some((name, self.block()?));
+            self.wrap(block, SyntaxKind::Block);
+            self.wrap(checkpoint, SyntaxKind::CatchClause);
+        }
+
+        let mut finally = None;
+        let checkpoint = self.checkpoint();
+        if self.matches_any(&[&TokenType::Finally]) {
+            let block = self.checkpoint();
+            self.consume(&TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            finally = Some(self.block()?);
+            self.wrap(block, SyntaxKind::Block);
+            self.wrap(checkpoint, SyntaxKind::FinallyClause);
+        }
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(
+                &keyword,
+                ErrorCode::ExpectedToken,
+                "Expect 'catch' or 'finally' after try block.",
+            ));
+        }
+
+        Ok(Stmt::Try(body, catch, finally))
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
+        let expr = self.or()?;
+
+        if self.matches_any(&[&TokenType::Equal]) {
+            let equals = self.previous();
+            let value = self.assignment()?;
+            self.wrap(checkpoint, SyntaxKind::AssignExpr);
+
+            match expr {
+                Expr::Variable(_, name) => {
+                    return Ok(Expr::Assign(Depth::default(), name, Box::new(value)));
+                }
+                Expr::Get(object, name) => return Ok(Expr::Set(object, name, Box::new(value))),
+                _ => {}
+            }
+
+            self.error(
+                &equals,
+                ErrorCode::InvalidAssignmentTarget,
+                "Invalid assignment target.",
+            );
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.and()?;
+
+        while self.matches_any(&[&TokenType::Or]) {
+            let operator = self.previous();
+            let right = self.and()?;
+            self.wrap(checkpoint, SyntaxKind::LogicalExpr);
+            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.equality()?;
+
+        while self.matches_any(&[&TokenType::And]) {
+            let operator = self.previous();
+            let right = self.equality()?;
+            self.wrap(checkpoint, SyntaxKind::LogicalExpr);
+            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
         let mut expr: Expr = self.comparison()?;
 
-        while (self.matches_any(&[&TokenType::BangEqual, &TokenType::EqualEqual])) {
+        while self.matches_any(&[&TokenType::BangEqual, &TokenType::EqualEqual]) {
             let operator = self.previous();
             let right = self.comparison()?;
+            self.wrap(checkpoint, SyntaxKind::BinaryExpr);
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
 
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, String> {
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
         let mut expr: Expr = self.term()?;
 
         while self.matches_any(&[
@@ -74,46 +466,112 @@ impl<'a> Parser<'a> {
         ]) {
             let operator = self.previous();
             let right = self.term()?;
+            self.wrap(checkpoint, SyntaxKind::BinaryExpr);
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
         let mut expr = self.factor()?;
         while self.matches_any(&[&TokenType::Minus, &TokenType::Plus]) {
             let operator = self.previous();
             let right = self.factor()?;
+            self.wrap(checkpoint, SyntaxKind::BinaryExpr);
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
         let mut expr = self.unary()?;
 
         while self.matches_any(&[&TokenType::Slash, &TokenType::Star]) {
             let operator = self.previous();
             let right = self.unary()?;
+            self.wrap(checkpoint, SyntaxKind::BinaryExpr);
             expr = Expr::Binary(Box::new(expr), operator, Box::new(right));
         }
 
-        return Ok(expr);
+        Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
         if self.matches_any(&[&TokenType::Bang, &TokenType::Minus]) {
             let operator = self.previous();
             let right = self.unary()?;
+            self.wrap(checkpoint, SyntaxKind::UnaryExpr);
             return Ok(Expr::Unary(operator, Box::new(right)));
         }
 
-        return self.primary();
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
+        let mut expr = self.primary()?;
+
+        loop {
+            let arguments = self.checkpoint();
+            if self.matches_any(&[&TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+                self.wrap(arguments, SyntaxKind::ArgList);
+                self.wrap(checkpoint, SyntaxKind::CallExpr);
+            } else if self.matches_any(&[&TokenType::Dot]) {
+                let name = self.consume_identifier("Expect property name after '.'.")?;
+                self.wrap(checkpoint, SyntaxKind::GetExpr);
+                expr = Expr::Get(Box::new(expr), name);
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = Vec::new();
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= 255 {
+                    self.error(
+                        &self.peek(),
+                        ErrorCode::TooManyArguments,
+                        "Can't have more than 255 arguments.",
+                    );
+                }
+
+                arguments.push(self.expression()?);
+                if !self.matches_any(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(&TokenType::RightParen, "Expect ')' after arguments.")?;
+        Ok(Expr::Call(Box::new(callee), paren, arguments))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let checkpoint = self.checkpoint();
+        let expr = self.primary_expr()?;
+        let kind = match expr {
+            Expr::Grouping(_) => SyntaxKind::GroupingExpr,
+            Expr::Super(..) => SyntaxKind::SuperExpr,
+            Expr::This(..) => SyntaxKind::ThisExpr,
+            Expr::Variable(..) => SyntaxKind::VariableExpr,
+            _ => SyntaxKind::LiteralExpr,
+        };
+        self.wrap(checkpoint, kind);
+        Ok(expr)
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary_expr(&mut self) -> Result<Expr, ParseError> {
         if self.matches_any(&[&TokenType::False]) {
             return Ok(Expr::Literal(LiteralValue::False));
         } else if self.matches_any(&[&TokenType::True]) {
@@ -124,33 +582,110 @@ impl<'a> Parser<'a> {
 
         if self.matches_any(&[&TokenType::Literal]) {
             let literal = self.previous();
-            match literal.literal.unwrap() {
-                Literal::String(s) => return Ok(Expr::Literal(LiteralValue::String(s))),
-                Literal::Number(n) => return Ok(Expr::Literal(LiteralValue::Number(n))),
-                _ => {}
-            }
+            return Ok(match literal.literal.clone().unwrap() {
+                Literal::String(s) => Expr::Literal(LiteralValue::String(s)),
+                Literal::Interpolation(parts) => self.interpolation(parts)?,
+                Literal::Number(n) => Expr::Literal(LiteralValue::Number(n)),
+                Literal::Identifier(_) => Expr::Variable(Depth::default(), literal),
+            });
+        }
+
+        if self.matches_any(&[&TokenType::Super]) {
+            let keyword = self.previous();
+            self.consume(&TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self.consume_identifier("Expect superclass method name.")?;
+            return Ok(Expr::Super(Depth::default(), keyword, method));
+        }
+
+        if self.matches_any(&[&TokenType::This]) {
+            return Ok(Expr::This(Depth::default(), self.previous()));
         }
 
         if self.matches_any(&[&TokenType::LeftParen]) {
             let expression = self.expression()?;
-            self.consume(&TokenType::RightParen, "Expected ')' after expression.");
+            self.consume(&TokenType::RightParen, "Expected ')' after expression.")?;
             return Ok(Expr::Grouping(Box::new(expression)));
         }
 
-        Err(self.error(&self.peek(), "Expected expression."))
+        Err(self.error(
+            &self.peek(),
+            ErrorCode::ExpectedExpression,
+            "Expected expression.",
+        ))
     }
 
-    fn consume(&mut self, token_type: &TokenType, message: &str) -> Token {
+    /// Parses each `${...}` segment of an interpolated string with a nested
+    /// parser over the segment's own tokens.
+    fn interpolation(&mut self, parts: Vec<StringPart>) -> Result<Expr, ParseError> {
+        let mut exprs = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Text(s) => exprs.push(Expr::Literal(LiteralValue::String(s))),
+                StringPart::Code(tokens) => {
+                    let mut parser = Parser::new(tokens.into_iter().map(Ok), &mut *self.lox);
+                    let expr = parser.expression().and_then(|expr| {
+                        if parser.is_at_end() {
+                            return Ok(expr);
+                        }
+                        Err(parser.error(
+                            &parser.peek(),
+                            ErrorCode::ExpectedToken,
+                            "Expect '}' after interpolated expression.",
+                        ))
+                    });
+                    exprs.push(expr?);
+                }
+            }
+        }
+
+        Ok(Expr::Interpolation(exprs))
+    }
+
+    fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<Token, ParseError> {
         if self.check(token_type) {
-            return self.advance();
+            return Ok(self.advance());
+        }
+
+        Err(self.error(&self.peek(), ErrorCode::ExpectedToken, message))
+    }
+
+    fn consume_identifier(&mut self, message: &str) -> Result<Token, ParseError> {
+        if self.check_identifier() {
+            return Ok(self.advance());
         }
 
-        panic!("{:?}", self.error(&self.peek(), message))
+        Err(self.error(&self.peek(), ErrorCode::ExpectedName, message))
     }
 
-    fn error(&mut self, token: &Token, message: &str) -> String {
-        self.lox.error_parser(token, message);
-        return "ParseError".into();
+    fn checkpoint(&self) -> usize {
+        self.syntax.as_ref().map_or(0, SyntaxBuilder::checkpoint)
+    }
+
+    /// Wraps everything consumed since `checkpoint` into a syntax node.
+    fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        if let Some(syntax) = &mut self.syntax {
+            syntax.wrap(checkpoint, kind);
+        }
+    }
+
+    /// Wraps the tokens of a rule into a node only if the rule parsed, so a
+    /// failed one is left flat inside the enclosing error node.
+    fn node<T>(
+        &mut self,
+        checkpoint: usize,
+        kind: SyntaxKind,
+        result: Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if result.is_ok() {
+            self.wrap(checkpoint, kind);
+        }
+        result
+    }
+
+    fn error(&mut self, token: &Token, code: ErrorCode, message: &str) -> ParseError {
+        let diagnostic = Diagnostic::error(message, token.span, token.line).with_code(code);
+        self.lox.report(diagnostic);
+        ParseError
     }
 
     fn matches_any(&mut self, types: &[&TokenType]) -> bool {
@@ -161,19 +696,41 @@ impl<'a> Parser<'a> {
             }
         }
 
-        return false;
+        false
+    }
+
+    /// Takes the next token from the stream, reporting any lexical errors
+    /// on the way. A stream that ends without `Eof` gets one at its end.
+    fn next_token(&mut self) -> Token {
+        loop {
+            match self.tokens.next() {
+                Some(Ok(token)) => return token,
+                Some(Err(err)) => {
+                    self.lox.report(err.to_diagnostic());
+                }
+                None => {
+                    let end = Span::new(self.current.span.end, self.current.span.end);
+                    let (line, column) = (self.current.line, self.current.column);
+                    return Token::new(TokenType::Eof, String::new(), None, line, column, end);
+                }
+            }
+        }
     }
 
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
-            self.current += 1;
+            let next = self.next_token();
+            self.previous = std::mem::replace(&mut self.current, next);
+            if let Some(syntax) = &mut self.syntax {
+                syntax.token(self.previous.clone());
+            }
         }
 
-        return self.previous();
+        self.previous()
     }
 
     fn previous(&mut self) -> Token {
-        return self.tokens[self.current - 1].clone();
+        self.previous.clone()
     }
 
     fn check(&self, t: &TokenType) -> bool {
@@ -181,14 +738,67 @@ impl<'a> Parser<'a> {
             return false;
         }
 
-        return self.peek().token_type == *t;
+        self.peek().token_type == *t
+    }
+
+    fn check_identifier(&self) -> bool {
+        matches!(self.peek().literal, Some(Literal::Identifier(_)))
     }
 
     fn is_at_end(&self) -> bool {
-        return self.peek().token_type == TokenType::Eof;
+        self.peek().token_type == TokenType::Eof
     }
 
     fn peek(&self) -> Token {
-        return self.tokens[self.current].clone();
+        self.current.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_parse_multiple_statements() {
+        let mut lox = Lox::new();
+        let tokens = Scanner::new("print 1 + 2; 3;");
+        let statements = Parser::new(tokens, &mut lox).parse();
+        assert!(!lox.had_error);
+
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(statements[0], Stmt::Print(_, Expr::Binary(..))));
+        assert!(matches!(statements[1], Stmt::Expression(Expr::Literal(..))));
+    }
+
+    #[test]
+    fn test_for_loop_desugars_into_while() {
+        let mut lox = Lox::new();
+        let tokens = Scanner::new("for (var i = 0; i < 3; i = i + 1) print i;");
+        let statements = Parser::new(tokens, &mut lox).parse();
+        assert!(!lox.had_error);
+
+        let [Stmt::Block(outer)] = statements.as_slice() else {
+            panic!("expected a single block");
+        };
+        assert!(matches!(outer[0], Stmt::Var(..)));
+        let Stmt::While(_, _, body) = &outer[1] else {
+            panic!("expected a while loop");
+        };
+        assert!(matches!(**body, Stmt::Block(_)));
+    }
+
+    #[test]
+    fn test_recovers_and_reports_every_syntax_error() {
+        let mut lox = Lox::new();
+        lox.set_error_output(std::io::sink());
+        let source = "var = 1;\nprint 1;\nprint (2;\nfun f( {}\nclass {}\nprint 3;\nvar b = ;";
+        let tokens = Scanner::new(source);
+        let statements = Parser::new(tokens, &mut lox).parse();
+
+        let lines: Vec<_> = lox.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![1, 3, 4, 5, 7]);
+        assert_eq!(statements.len(), 2);
+        assert!(statements.iter().all(|s| matches!(s, Stmt::Print(..))));
     }
 }
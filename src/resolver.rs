@@ -0,0 +1,551 @@
+use crate::Lox;
+use crate::diagnostic::Diagnostic;
+use crate::error_code::ErrorCode;
+use crate::expression::{Depth, Expr, FunctionDecl, Stmt, StmtVisitor, Visitor};
+use crate::token::{Span, Token};
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Whether a name in a local scope has finished initializing, and where it
+/// was declared.
+struct Binding {
+    defined: bool,
+    span: Span,
+}
+
+/// Walks the AST once before execution, recording on each local variable
+/// reference how many scopes away it is from its declaration.
+pub struct Resolver<'a> {
+    lox: &'a mut Lox,
+    scopes: Vec<HashMap<String, Binding>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(lox: &'a mut Lox) -> Resolver<'a> {
+        Resolver {
+            lox,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+        }
+    }
+
+    pub fn resolve(mut self, statements: &[Stmt]) {
+        self.resolve_statements(statements);
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        expr.accept(self);
+    }
+
+    fn resolve_function(&mut self, function: &FunctionDecl, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    fn resolve_local(&mut self, depth: &Depth, name: &Token) {
+        let found = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(&name.lexeme));
+        depth.set(found);
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        let binding = Binding {
+            defined: false,
+            span: name.span,
+        };
+        if let Some(previous) = scope.insert(name.lexeme.clone(), binding) {
+            let diagnostic = Diagnostic::error(
+                "Already a variable with this name in this scope.",
+                name.span,
+                name.line,
+            )
+            .with_code(ErrorCode::DuplicateDeclaration)
+            .with_label(previous.span, "previously declared here");
+            self.lox.report(diagnostic);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        self.define_name(&name.lexeme, name.span);
+    }
+
+    fn define_name(&mut self, name: &str, span: Span) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(
+                name.to_string(),
+                Binding {
+                    defined: true,
+                    span,
+                },
+            );
+        }
+    }
+}
+
+impl<'a> Visitor<()> for Resolver<'a> {
+    fn visit_assign_expr(&mut self, expr: &Expr) {
+        let Expr::Assign(depth, name, value) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(value);
+        self.resolve_local(depth, name);
+    }
+
+    fn visit_binary_expr(&mut self, expr: &Expr) {
+        let Expr::Binary(left, _, right) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(left);
+        self.resolve_expr(right);
+    }
+
+    fn visit_call_expr(&mut self, expr: &Expr) {
+        let Expr::Call(callee, _, arguments) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(callee);
+        for argument in arguments {
+            self.resolve_expr(argument);
+        }
+    }
+
+    fn visit_get_expr(&mut self, expr: &Expr) {
+        let Expr::Get(object, _) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(object);
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &Expr) {
+        let Expr::Grouping(expression) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(expression);
+    }
+
+    fn visit_interpolation_expr(&mut self, expr: &Expr) {
+        let Expr::Interpolation(parts) = expr else {
+            unreachable!()
+        };
+
+        for part in parts {
+            self.resolve_expr(part);
+        }
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &Expr) {}
+
+    fn visit_logical_expr(&mut self, expr: &Expr) {
+        let Expr::Logical(left, _, right) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(left);
+        self.resolve_expr(right);
+    }
+
+    fn visit_set_expr(&mut self, expr: &Expr) {
+        let Expr::Set(object, _, value) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(value);
+        self.resolve_expr(object);
+    }
+
+    fn visit_super_expr(&mut self, expr: &Expr) {
+        let Expr::Super(depth, keyword, _) = expr else {
+            unreachable!()
+        };
+
+        match self.current_class {
+            ClassType::None => self.lox.error_parser(
+                keyword,
+                ErrorCode::SuperOutsideClass,
+                "Can't use 'super' outside of a class.",
+            ),
+            ClassType::Class => self.lox.error_parser(
+                keyword,
+                ErrorCode::SuperWithoutSuperclass,
+                "Can't use 'super' in a class with no superclass.",
+            ),
+            ClassType::Subclass => {}
+        }
+
+        self.resolve_local(depth, keyword);
+    }
+
+    fn visit_this_expr(&mut self, expr: &Expr) {
+        let Expr::This(depth, keyword) = expr else {
+            unreachable!()
+        };
+
+        if self.current_class == ClassType::None {
+            self.lox.error_parser(
+                keyword,
+                ErrorCode::ThisOutsideClass,
+                "Can't use 'this' outside of a class.",
+            );
+            return;
+        }
+
+        self.resolve_local(depth, keyword);
+    }
+
+    fn visit_unary_expr(&mut self, expr: &Expr) {
+        let Expr::Unary(_, right) = expr else {
+            unreachable!()
+        };
+
+        self.resolve_expr(right);
+    }
+
+    fn visit_variable_expr(&mut self, expr: &Expr) {
+        let Expr::Variable(depth, name) = expr else {
+            unreachable!()
+        };
+
+        if let Some(scope) = self.scopes.last()
+            && scope
+                .get(&name.lexeme)
+                .is_some_and(|binding| !binding.defined)
+        {
+            self.lox.error_parser(
+                name,
+                ErrorCode::ReadInOwnInitializer,
+                "Can't read local variable in its own initializer.",
+            );
+        }
+
+        self.resolve_local(depth, name);
+    }
+}
+
+impl<'a> StmtVisitor<()> for Resolver<'a> {
+    fn visit_block_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Block(statements) = stmt else {
+            unreachable!()
+        };
+
+        self.begin_scope();
+        self.resolve_statements(statements);
+        self.end_scope();
+    }
+
+    fn visit_class_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Class(name, superclass, methods) = stmt else {
+            unreachable!()
+        };
+
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let Expr::Variable(_, superclass_name) = superclass
+                && superclass_name.lexeme == name.lexeme
+            {
+                self.lox.error_parser(
+                    superclass_name,
+                    ErrorCode::InheritFromSelf,
+                    "A class can't inherit from itself.",
+                );
+            }
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.define_name("super", name.span);
+        }
+
+        self.begin_scope();
+        self.define_name("this", name.span);
+
+        for method in methods {
+            let function_type = if method.name.lexeme == "init" {
+                FunctionType::Initializer
+            } else {
+                FunctionType::Method
+            };
+            self.resolve_function(method, function_type);
+        }
+
+        self.end_scope();
+
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn visit_expression_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Expression(expression) = stmt else {
+            unreachable!()
+        };
+
+        self.resolve_expr(expression);
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Function(function) = stmt else {
+            unreachable!()
+        };
+
+        self.declare(&function.name);
+        self.define(&function.name);
+
+        self.resolve_function(function, FunctionType::Function);
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::If(_, condition, then_branch, else_branch) = stmt else {
+            unreachable!()
+        };
+
+        self.resolve_expr(condition);
+        then_branch.accept(self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept(self);
+        }
+    }
+
+    fn visit_print_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Print(_, expression) = stmt else {
+            unreachable!()
+        };
+
+        self.resolve_expr(expression);
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Return(keyword, value) = stmt else {
+            unreachable!()
+        };
+
+        if self.current_function == FunctionType::None {
+            self.lox.error_parser(
+                keyword,
+                ErrorCode::TopLevelReturn,
+                "Can't return from top-level code.",
+            );
+        }
+
+        if let Some(value) = value {
+            if self.current_function == FunctionType::Initializer {
+                self.lox.error_parser(
+                    keyword,
+                    ErrorCode::ReturnValueFromInitializer,
+                    "Can't return a value from an initializer.",
+                );
+            }
+
+            self.resolve_expr(value);
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Throw(_, value) = stmt else {
+            unreachable!()
+        };
+
+        self.resolve_expr(value);
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Try(body, catch, finally) = stmt else {
+            unreachable!()
+        };
+
+        self.begin_scope();
+        self.resolve_statements(body);
+        self.end_scope();
+
+        // The error variable shares a scope with the catch body.
+        if let Some((name, body)) = catch {
+            self.begin_scope();
+            self.declare(name);
+            self.define(name);
+            self.resolve_statements(body);
+            self.end_scope();
+        }
+
+        if let Some(finally) = finally {
+            self.begin_scope();
+            self.resolve_statements(finally);
+            self.end_scope();
+        }
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::Var(name, initializer) = stmt else {
+            unreachable!()
+        };
+
+        self.declare(name);
+        if let Some(initializer) = initializer {
+            self.resolve_expr(initializer);
+        }
+        self.define(name);
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &Stmt) {
+        let Stmt::While(_, condition, body) = stmt else {
+            unreachable!()
+        };
+
+        self.resolve_expr(condition);
+        body.accept(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str, lox: &mut Lox) -> Vec<Stmt> {
+        let tokens = Scanner::new(source);
+        let statements = Parser::new(tokens, lox).parse();
+        Resolver::new(lox).resolve(&statements);
+        statements
+    }
+
+    #[test]
+    fn test_resolves_local_depths_and_leaves_globals_dynamic() {
+        let mut lox = Lox::new();
+        let statements = resolve("var g; { var a; { print a; print g; } }", &mut lox);
+        assert!(!lox.had_error);
+
+        let Stmt::Block(outer) = &statements[1] else {
+            panic!("expected a block");
+        };
+        let Stmt::Block(inner) = &outer[1] else {
+            panic!("expected a block");
+        };
+        let depths: Vec<_> = inner
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Print(_, Expr::Variable(depth, _)) => depth.get(),
+                _ => panic!("expected a printed variable"),
+            })
+            .collect();
+        assert_eq!(depths, vec![Some(1), None]);
+    }
+
+    #[test]
+    fn test_reports_top_level_return() {
+        let mut lox = Lox::new();
+        lox.set_error_output(std::io::sink());
+        resolve("return 1;", &mut lox);
+
+        assert!(lox.had_error);
+    }
+
+    /// The diagnostics reported while resolving `source`.
+    fn diagnostics(source: &str) -> Vec<Diagnostic> {
+        let mut lox = Lox::new();
+        lox.set_error_output(std::io::sink());
+        resolve(source, &mut lox);
+        lox.errors
+    }
+
+    #[test]
+    fn test_reports_a_local_read_in_its_own_initializer() {
+        let errors = diagnostics("{ var a = a; }");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(ErrorCode::ReadInOwnInitializer));
+        assert_eq!(errors[0].span, Span::new(10, 11));
+        assert!(diagnostics("var a = a;").is_empty());
+    }
+
+    #[test]
+    fn test_reports_a_duplicate_declaration_with_the_first_one() {
+        let errors = diagnostics("{ var a; fun a() {} }");
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Some(ErrorCode::DuplicateDeclaration));
+        assert_eq!(errors[0].span, Span::new(13, 14));
+        assert_eq!(errors[0].labels.len(), 1);
+        assert_eq!(errors[0].labels[0].span, Span::new(6, 7));
+        assert_eq!(errors[0].labels[0].message, "previously declared here");
+        assert!(diagnostics("var a; var a;").is_empty());
+    }
+
+    #[test]
+    fn test_reports_this_and_super_outside_their_classes() {
+        let cases = [
+            ("fun f() { return this; }", ErrorCode::ThisOutsideClass),
+            ("print super.f;", ErrorCode::SuperOutsideClass),
+            (
+                "class A { f() { super.f(); } }",
+                ErrorCode::SuperWithoutSuperclass,
+            ),
+        ];
+        for (source, code) in cases {
+            let errors = diagnostics(source);
+            assert_eq!(errors.len(), 1);
+            assert_eq!(errors[0].code, Some(code));
+            let span = errors[0].span;
+            assert!(matches!(&source[span.start..span.end], "this" | "super"));
+        }
+    }
+}
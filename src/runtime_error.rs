@@ -1,16 +1,139 @@
-use crate::Token;
+use crate::diagnostic::Diagnostic;
+use crate::error_code::ErrorCode;
+use crate::interpreter::LoxValue;
+use crate::lox_class::LoxClass;
+use crate::lox_instance::LoxInstance;
+use crate::token::{Span, Token, TokenType};
+use crate::value::Value;
+use std::cell::RefCell;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+
+/// A call that was in progress when an error was raised.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub function: String,
+    /// The closing paren of the call expression that entered `function`.
+    pub call_site: Token,
+}
 
 #[derive(Debug)]
 pub struct RuntimeError {
-    pub token: Token,
+    // Boxed to keep `Result<_, RuntimeError>` small on the hot path.
+    pub token: Box<Token>,
+    pub code: ErrorCode,
     pub message: String,
+    /// The calls active when the error was raised, innermost first.
+    pub trace: Vec<CallFrame>,
+    pub(crate) thrown: Option<LoxValue>,
 }
 
 impl RuntimeError {
-    pub fn new(token: Token, message: &str) -> Self {
+    pub fn new(token: Token, code: ErrorCode, message: &str) -> Self {
         RuntimeError {
-            token,
+            token: Box::new(token),
+            code,
             message: message.to_string(),
+            trace: Vec::new(),
+            thrown: None,
+        }
+    }
+
+    /// An error raised by `throw`. Its message is the value's `message` field
+    /// if it has a string one, or else the value itself.
+    pub fn thrown(token: Token, value: LoxValue) -> Self {
+        let message = match &value {
+            LoxValue::Instance(instance) => match instance.borrow().field("message") {
+                Some(LoxValue::String(message)) => message,
+                _ => value.to_string(),
+            },
+            _ => value.to_string(),
+        };
+
+        RuntimeError {
+            thrown: Some(value),
+            ..RuntimeError::new(token, ErrorCode::UncaughtThrow, &message)
+        }
+    }
+
+    /// The value passed to `throw`, if the error came from a script rather
+    /// than from the interpreter itself.
+    pub fn thrown_value(&self) -> Option<Value> {
+        self.thrown.clone().map(Value::from)
+    }
+
+    /// Turns the error into the value a `catch` clause binds: whatever was
+    /// thrown, or an instance of `error_class` with `message` and `line`
+    /// fields.
+    pub fn into_value(self, error_class: Rc<LoxClass>) -> LoxValue {
+        if let Some(value) = self.thrown {
+            return value;
+        }
+
+        let mut error = LoxInstance::new(error_class);
+        error.set_field("message", LoxValue::String(self.message));
+        error.set_field("line", LoxValue::Number(self.token.line as f64));
+        LoxValue::Instance(Rc::new(RefCell::new(error)))
+    }
+
+    /// Creates an error from host code that has no token of its own; the
+    /// interpreter relocates it to the call site.
+    pub fn native(message: &str) -> Self {
+        let token = Token::new(TokenType::Eof, String::new(), None, 0, 0, Span::default());
+        RuntimeError::new(token, ErrorCode::NativeError, message)
+    }
+
+    /// An error writing what the program prints, such as a closed pipe.
+    pub fn output(token: Token, err: &io::Error) -> Self {
+        RuntimeError::new(
+            token,
+            ErrorCode::OutputFailed,
+            &format!("Could not write program output: {}.", err),
+        )
+    }
+
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        let diagnostic =
+            Diagnostic::error(&self.message, self.token.span, self.token.line).with_code(self.code);
+        self.traceback()
+            .into_iter()
+            .fold(diagnostic, |diagnostic, (function, line)| {
+                diagnostic.with_note(&format!("in {} at line {}", function, line))
+            })
+    }
+
+    /// Pairs each frame's function with the line it was executing, innermost
+    /// first and ending with the top-level script. Empty if the error was
+    /// raised outside of any call.
+    pub fn traceback(&self) -> Vec<(String, usize)> {
+        if self.trace.is_empty() {
+            return Vec::new();
+        }
+
+        // A frame is executing the line its callee was called from; the
+        // innermost one is at the error itself.
+        let lines = std::iter::once(self.token.line)
+            .chain(self.trace.iter().map(|frame| frame.call_site.line));
+        let functions = self
+            .trace
+            .iter()
+            .map(|frame| format!("{}()", frame.function))
+            .chain(std::iter::once(String::from("script")));
+        functions.zip(lines).collect()
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.trace.is_empty() {
+            return write!(f, "{}\n[line {}]", self.message, self.token.line);
+        }
+
+        write!(f, "{}", self.message)?;
+        for (function, line) in self.traceback() {
+            write!(f, "\n[line {}] in {}", line, function)?;
         }
+        Ok(())
     }
 }
@@ -1,15 +1,19 @@
-use crate::Lox;
-use crate::token::{Literal, Token, TokenType};
+use crate::diagnostic::Diagnostic;
+use crate::error_code::ErrorCode;
+use crate::token::{Literal, Span, StringPart, Token, TokenType, Trivia, TriviaKind};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::{error, fmt};
 
 lazy_static! {
     static ref KEYWORDS: HashMap<&'static str, TokenType> = {
         let mut m = HashMap::new();
         m.insert("and", TokenType::And);
+        m.insert("catch", TokenType::Catch);
         m.insert("class", TokenType::Class);
         m.insert("else", TokenType::Else);
         m.insert("false", TokenType::False);
+        m.insert("finally", TokenType::Finally);
         m.insert("for", TokenType::For);
         m.insert("fun", TokenType::Fun);
         m.insert("if", TokenType::If);
@@ -19,43 +23,87 @@ lazy_static! {
         m.insert("return", TokenType::Return);
         m.insert("super", TokenType::Super);
         m.insert("this", TokenType::This);
+        m.insert("throw", TokenType::Throw);
         m.insert("true", TokenType::True);
+        m.insert("try", TokenType::Try);
         m.insert("var", TokenType::Var);
         m.insert("while", TokenType::While);
         m
     };
 }
 
-pub struct Scanner<'a> {
-    lox: &'a mut Lox,
-    pub source: String,
-    pub tokens: Vec<Token>,
+/// A malformed piece of source text. Scanning carries on after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub span: Span,
+    pub line: usize,
+}
+
+impl LexError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::error(&self.message, self.span, self.line).with_code(self.code)
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_diagnostic())
+    }
+}
+
+impl error::Error for LexError {}
+
+/// Produces tokens lazily from borrowed source, ending with a single `Eof`.
+/// Positions are byte offsets into `source`; columns count characters.
+pub struct Scanner<'src> {
+    source: &'src str,
+    /// Items scanned but not yet returned. A single string literal can yield
+    /// several escape errors before its token.
+    pending: VecDeque<Result<Token, LexError>>,
+    finished: bool,
+    lossless: bool,
+    /// Trivia waiting to be attached to the next token.
+    trivia: Vec<Trivia>,
     current: usize,
     start: usize,
     line: usize,
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    offset: usize,
 }
 
-impl<'a> Scanner<'a> {
-    pub fn new(source: String, lox: &'a mut Lox) -> Scanner<'a> {
+impl<'src> Scanner<'src> {
+    pub fn new(source: &'src str) -> Scanner<'src> {
+        Scanner::with_offset(source, 0)
+    }
+
+    /// `offset` is where `source` begins in the interpreter's source map, so
+    /// token spans stay unique across every scanned input.
+    pub fn with_offset(source: &'src str, offset: usize) -> Scanner<'src> {
         Scanner {
-            lox,
             source,
-            tokens: Vec::new(),
+            pending: VecDeque::new(),
+            finished: false,
+            lossless: false,
+            trivia: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            column: 0,
+            start_line: 1,
+            start_column: 1,
+            offset,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Vec<Token> {
-        while !self.is_at_end() {
-            self.start = self.current;
-            self.scan_token()
-        }
-
-        self.tokens
-            .push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
-        return self.tokens.clone();
+    /// Keeps whitespace, comments and unscannable text as trivia on the
+    /// following token, so the source can be rebuilt from the tokens exactly.
+    pub fn lossless(mut self) -> Scanner<'src> {
+        self.lossless = true;
+        self
     }
 
     fn scan_token(&mut self) {
@@ -105,15 +153,22 @@ impl<'a> Scanner<'a> {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_trivia(TriviaKind::LineComment);
+                } else if self.matches('*') {
+                    self.block_comment();
                 } else {
                     self.add_token_without_literal(TokenType::Slash);
                 }
             }
-            ' ' => {}
-            '\r' => {}
-            '\t' => {}
+            ' ' | '\r' | '\t' => {
+                while matches!(self.peek(), ' ' | '\r' | '\t') {
+                    self.advance();
+                }
+                self.add_trivia(TriviaKind::Whitespace);
+            }
             '\n' => {
-                self.line += 1;
+                self.new_line();
+                self.add_trivia(TriviaKind::Newline);
             }
             '"' => {
                 self.string();
@@ -126,12 +181,40 @@ impl<'a> Scanner<'a> {
                 } else if self.is_alphabet(c) {
                     self.identifier();
                 } else {
-                    self.lox.error_lexer(self.line, "Unexpected character.");
+                    self.error(
+                        self.start_line,
+                        self.span(),
+                        ErrorCode::UnexpectedCharacter,
+                        "Unexpected character.",
+                    );
+                    self.add_trivia(TriviaKind::Skipped);
                 }
             }
         }
     }
 
+    fn block_comment(&mut self) {
+        while !(self.peek() == '*' && self.peek_next() == '/') {
+            if self.is_at_end() {
+                self.error(
+                    self.start_line,
+                    self.span(),
+                    ErrorCode::UnterminatedComment,
+                    "Unterminated block comment.",
+                );
+                break;
+            }
+
+            if self.advance() == '\n' {
+                self.new_line();
+            }
+        }
+
+        self.matches('*');
+        self.matches('/');
+        self.add_trivia(TriviaKind::BlockComment);
+    }
+
     fn identifier(&mut self) {
         while self.is_alpha_numeric(self.peek()) {
             self.advance();
@@ -144,11 +227,25 @@ impl<'a> Scanner<'a> {
             None => self.add_token(TokenType::Literal, Some(Literal::Identifier(text.into()))),
         }
     }
+
+    #[cfg(feature = "unicode-identifiers")]
+    fn is_alpha_numeric(&self, c: char) -> bool {
+        unicode_ident::is_xid_continue(c)
+    }
+
+    #[cfg(feature = "unicode-identifiers")]
+    fn is_alphabet(&self, c: char) -> bool {
+        c == '_' || unicode_ident::is_xid_start(c)
+    }
+
+    #[cfg(not(feature = "unicode-identifiers"))]
     fn is_alpha_numeric(&self, c: char) -> bool {
         self.is_alphabet(c) || self.is_digit(c)
     }
+
+    #[cfg(not(feature = "unicode-identifiers"))]
     fn is_alphabet(&self, c: char) -> bool {
-        (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
+        c.is_ascii_alphabetic() || c == '_'
     }
 
     fn number(&mut self) {
@@ -173,58 +270,176 @@ impl<'a> Scanner<'a> {
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-        return self.source.chars().nth(self.current + 1).unwrap();
+        let mut chars = self.source[self.current..].chars();
+        chars.next();
+        chars.next().unwrap_or('\0')
     }
 
     fn is_digit(&self, c: char) -> bool {
-        c.is_digit(10)
+        c.is_ascii_digit()
     }
     fn string(&mut self) {
+        let mut parts = Vec::new();
+        let mut value = String::new();
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            match self.advance() {
+                '$' if self.matches('{') => {
+                    parts.push(StringPart::Text(std::mem::take(&mut value)));
+                    parts.push(StringPart::Code(self.interpolation()));
+                }
+                '\n' => {
+                    self.new_line();
+                    value.push('\n');
+                }
+                '\\' => {
+                    if let Some(c) = self.escape() {
+                        value.push(c);
+                    }
+                }
+                c => value.push(c),
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            self.lox.error_lexer(self.line, "Unterminated string");
+            self.error(
+                self.start_line,
+                self.span(),
+                ErrorCode::UnterminatedString,
+                "Unterminated string",
+            );
+            self.add_trivia(TriviaKind::Skipped);
             return;
         }
 
-        let value: String = self.source[self.start..self.current].into();
-        self.add_token(TokenType::Literal, Some(Literal::String(value)));
+        self.advance(); // The closing '"'.
+        let literal = if parts.is_empty() {
+            Literal::String(value)
+        } else {
+            parts.push(StringPart::Text(value));
+            parts.retain(|part| *part != StringPart::Text(String::new()));
+            Literal::Interpolation(parts)
+        };
+        self.add_token(TokenType::Literal, Some(literal));
+    }
 
-        self.advance(); // should return '"'
+    /// Scans the tokens of a `${...}` segment up to its closing brace, which
+    /// becomes the segment's `Eof`. Strings inside the segment may themselves
+    /// be interpolated.
+    fn interpolation(&mut self) -> Vec<Token> {
+        let outer = std::mem::take(&mut self.pending);
+        let outer_trivia = std::mem::take(&mut self.trivia);
+        let (start, start_line, start_column) = (self.start, self.start_line, self.start_column);
+
+        let mut depth = 0;
+        while !self.is_at_end() {
+            self.begin_token();
+            match self.peek() {
+                '}' if depth == 0 => break,
+                '}' => depth -= 1,
+                '{' => depth += 1,
+                _ => {}
+            }
+            self.scan_token();
+        }
+
+        self.begin_token();
+        if !self.is_at_end() {
+            self.advance();
+        }
+        self.add_token(TokenType::Eof, None);
+
+        let scanned = std::mem::replace(&mut self.pending, outer);
+        self.trivia = outer_trivia;
+        (self.start, self.start_line, self.start_column) = (start, start_line, start_column);
+
+        // Errors inside the segment are reported before the string itself.
+        let mut tokens = Vec::new();
+        for item in scanned {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(err) => self.pending.push_back(Err(err)),
+            }
+        }
+        tokens
     }
 
-    fn peek(&self) -> char {
+    /// Decodes the escape sequence after a backslash, reporting an error
+    /// that points at the whole sequence if it is invalid.
+    fn escape(&mut self) -> Option<char> {
+        let escape_start = self.current - 1;
         if self.is_at_end() {
-            return '\0';
+            // Reported as an unterminated string.
+            return None;
+        }
+
+        let decoded = match self.advance() {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '$' => Ok('$'),
+            '0' => Ok('\0'),
+            'u' => self.unicode_escape(),
+            '\n' => {
+                self.new_line();
+                Err(String::from("Invalid escape sequence."))
+            }
+            c => Err(format!("Invalid escape sequence '\\{}'.", c)),
+        };
+
+        match decoded {
+            Ok(c) => Some(c),
+            Err(message) => {
+                let span = Span::new(self.offset + escape_start, self.offset + self.current);
+                self.error(self.line, span, ErrorCode::InvalidEscape, &message);
+                None
+            }
         }
-        return self.source.chars().nth(self.current).unwrap();
     }
 
-    fn matches(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
+    /// Decodes the `{XXXX}` part of a `\u{XXXX}` escape.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        if !self.matches('{') {
+            return Err(String::from("Expect '{' after '\\u'."));
         }
 
-        if self.source.chars().nth(self.current) != Some(expected) {
+        let mut digits = String::new();
+        while self.peek().is_ascii_hexdigit() {
+            digits.push(self.advance());
+        }
+
+        if !self.matches('}') {
+            return Err(String::from("Expect '}' after Unicode escape digits."));
+        }
+
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(String::from("Unicode escape must have 1 to 6 hex digits."));
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("Invalid Unicode code point '{}'.", digits))
+    }
+
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
+    }
+
+    fn matches(&mut self, expected: char) -> bool {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
-        return true;
+        self.advance();
+        true
     }
 
     fn advance(&mut self) -> char {
-        let response = self.source.chars().nth(self.current).unwrap();
-        self.current += 1;
-        response
+        let c = self.peek();
+        self.current += c.len_utf8();
+        self.column += 1;
+        c
     }
 
     fn add_token_without_literal(&mut self, token_type: TokenType) {
@@ -234,11 +449,216 @@ impl<'a> Scanner<'a> {
     fn add_token(&mut self, token_type: TokenType, literal: Option<Literal>) {
         let text: String = self.source[self.start..self.current].to_string();
 
-        self.tokens
-            .push(Token::new(token_type, text, literal, self.line));
+        let mut token = Token::new(
+            token_type,
+            text,
+            literal,
+            self.start_line,
+            self.start_column,
+            self.span(),
+        );
+        token.leading_trivia = std::mem::take(&mut self.trivia);
+        self.pending.push_back(Ok(token));
+    }
+
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        if self.lossless {
+            self.trivia.push(Trivia {
+                kind,
+                text: self.source[self.start..self.current].to_string(),
+                span: self.span(),
+            });
+        }
+    }
+
+    fn error(&mut self, line: usize, span: Span, code: ErrorCode, message: &str) {
+        self.pending.push_back(Err(LexError {
+            code,
+            message: message.to_string(),
+            span,
+            line,
+        }));
+    }
+
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column + 1;
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.offset + self.start, self.offset + self.current)
+    }
+
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 0;
     }
 
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 }
+
+impl Iterator for Scanner<'_> {
+    type Item = Result<Token, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Whitespace and comments produce nothing, so keep scanning until
+        // something is ready.
+        while self.pending.is_empty() && !self.finished {
+            self.begin_token();
+            if self.is_at_end() {
+                self.add_token_without_literal(TokenType::Eof);
+                self.finished = true;
+            } else {
+                self.scan_token();
+            }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> (Vec<Token>, Vec<LexError>) {
+        let (tokens, errors): (Vec<_>, Vec<_>) = Scanner::new(source).partition(Result::is_ok);
+        (
+            tokens.into_iter().map(Result::unwrap).collect(),
+            errors.into_iter().map(Result::unwrap_err).collect(),
+        )
+    }
+
+    #[test]
+    fn test_tokens_are_produced_lazily_and_end_with_one_eof() {
+        let mut scanner = Scanner::new("print 1; @");
+
+        let print = scanner.next().unwrap().unwrap();
+        assert_eq!(print.token_type, TokenType::Print);
+        // Nothing past the first token has been looked at yet.
+        assert_eq!(scanner.current, 5);
+
+        let rest: Vec<_> = scanner.collect();
+        assert_eq!(rest.len(), 4);
+        assert_eq!(
+            rest[2].as_ref().unwrap_err().code,
+            ErrorCode::UnexpectedCharacter
+        );
+        assert_eq!(rest[3].as_ref().unwrap().token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn test_string_value_excludes_quotes_and_decodes_escapes() {
+        let (tokens, errors) = scan(r#""a\n\t\\\"\0\u{41}\u{1F600}""#);
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::String(String::from("a\n\t\\\"\0A\u{1F600}")))
+        );
+        assert_eq!(tokens[0].span, Span::new(0, 28));
+    }
+
+    #[test]
+    fn test_interpolation_segments_are_nested_token_streams() {
+        let (tokens, errors) = scan(r#""a ${b + "c ${d}"} \${e}""#);
+
+        assert!(errors.is_empty());
+        let Some(Literal::Interpolation(parts)) = &tokens[0].literal else {
+            panic!("expected an interpolated string");
+        };
+        let [
+            StringPart::Text(a),
+            StringPart::Code(code),
+            StringPart::Text(e),
+        ] = parts.as_slice()
+        else {
+            panic!("unexpected parts {:?}", parts);
+        };
+        assert_eq!(a, "a ");
+        assert_eq!(e, " ${e}");
+
+        let types: Vec<_> = code.iter().map(|token| &token.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                &TokenType::Literal,
+                &TokenType::Plus,
+                &TokenType::Literal,
+                &TokenType::Eof
+            ]
+        );
+        assert!(matches!(
+            code[2].literal,
+            Some(Literal::Interpolation(ref inner)) if inner.len() == 2
+        ));
+        assert_eq!(tokens[1].token_type, TokenType::Eof);
+    }
+
+    #[test]
+    fn test_non_ascii_text_uses_byte_spans_and_char_columns() {
+        let (tokens, errors) = scan("// ünïcode ✓\n\"naïve 😀\" + ;");
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::String(String::from("naïve 😀")))
+        );
+        assert_eq!(tokens[0].lexeme, "\"naïve 😀\"");
+        assert_eq!(tokens[0].span, Span::new(17, 30));
+        assert_eq!((tokens[1].line, tokens[1].column), (2, 11));
+    }
+
+    #[cfg(feature = "unicode-identifiers")]
+    #[test]
+    fn test_unicode_identifiers() {
+        let (tokens, errors) = scan("café_2 π");
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            tokens[0].literal,
+            Some(Literal::Identifier(String::from("café_2")))
+        );
+        assert_eq!(
+            tokens[1].literal,
+            Some(Literal::Identifier(String::from("π")))
+        );
+    }
+
+    #[test]
+    fn test_invalid_escape_is_reported_at_the_escape() {
+        let (_, errors) = scan(r#""ok \q""#);
+
+        let [error] = errors.as_slice() else {
+            panic!("expected one error");
+        };
+        assert_eq!(error.code, ErrorCode::InvalidEscape);
+        assert_eq!(error.span, Span::new(4, 6));
+    }
+
+    #[test]
+    fn test_lossless_scanning_attaches_trivia_to_the_next_token() {
+        let tokens: Vec<_> = Scanner::new("/* a */ x // b\n")
+            .lossless()
+            .map(Result::unwrap)
+            .collect();
+
+        let kinds: Vec<_> = tokens[0].leading_trivia.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![TriviaKind::BlockComment, TriviaKind::Whitespace]
+        );
+        let eof: Vec<_> = tokens[1]
+            .leading_trivia
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect();
+        assert_eq!(eof, vec![" ", "// b", "\n"]);
+
+        let (_, errors) = scan("/* never closed");
+        assert_eq!(errors[0].code, ErrorCode::UnterminatedComment);
+    }
+}
@@ -0,0 +1,376 @@
+use crate::Lox;
+use crate::diagnostic::Diagnostic;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::{Literal, Token, TriviaKind};
+use std::{fmt, io};
+
+/// Parses `source` into a lossless syntax tree. The tree is built even when
+/// there are syntax errors; the parts that failed to parse become
+/// [`SyntaxKind::Error`] nodes.
+pub fn parse(source: &str) -> (SyntaxNode, Vec<Diagnostic>) {
+    let mut lox = Lox::new();
+    lox.set_error_output(io::sink());
+    let tokens = Scanner::new(source).lossless();
+    let (root, _) = Parser::new(tokens, &mut lox).parse_lossless();
+    (root, lox.errors)
+}
+
+/// What a node in the concrete syntax tree represents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind {
+    Program,
+    ClassDecl,
+    FunDecl,
+    ParamList,
+    VarDecl,
+    Block,
+    ExprStmt,
+    ForStmt,
+    IfStmt,
+    PrintStmt,
+    ReturnStmt,
+    ThrowStmt,
+    TryStmt,
+    CatchClause,
+    FinallyClause,
+    WhileStmt,
+    AssignExpr,
+    BinaryExpr,
+    CallExpr,
+    ArgList,
+    GetExpr,
+    GroupingExpr,
+    LiteralExpr,
+    LogicalExpr,
+    SuperExpr,
+    ThisExpr,
+    UnaryExpr,
+    VariableExpr,
+    /// A declaration that failed to parse, along with the tokens skipped
+    /// while recovering from it.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+}
+
+/// A node of the lossless concrete syntax tree. Every token of the source,
+/// with its leading trivia, appears exactly once as a leaf, so the tree
+/// prints back to the original text byte for byte.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    pub fn child_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+
+    /// All tokens under this node in source order.
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+    }
+
+    pub fn first_token(&self) -> Option<&Token> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    /// The comments directly above this node, such as a doc comment on a
+    /// declaration.
+    pub fn leading_comments(&self) -> Vec<&str> {
+        let Some(token) = self.first_token() else {
+            return Vec::new();
+        };
+
+        token
+            .leading_trivia
+            .iter()
+            .filter(|trivia| {
+                matches!(
+                    trivia.kind,
+                    TriviaKind::LineComment | TriviaKind::BlockComment
+                )
+            })
+            .map(|trivia| trivia.text.as_str())
+            .collect()
+    }
+
+    fn write_text(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens() {
+            for trivia in &token.leading_trivia {
+                write!(f, "{}", trivia.text)?;
+            }
+            write!(f, "{}", token.lexeme)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the exact source text the node was parsed from.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write_text(f)
+    }
+}
+
+/// Assembles a tree from the tokens the parser consumes. Nodes are made
+/// after the fact: once a rule has parsed successfully, everything consumed
+/// since its checkpoint is wrapped into a node of the rule's kind.
+#[derive(Default)]
+pub struct SyntaxBuilder {
+    children: Vec<SyntaxElement>,
+}
+
+impl SyntaxBuilder {
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn token(&mut self, token: Token) {
+        self.children.push(SyntaxElement::Token(token));
+    }
+
+    pub fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        let children = self.children.split_off(checkpoint);
+        self.children
+            .push(SyntaxElement::Node(SyntaxNode { kind, children }));
+    }
+
+    pub fn finish(self) -> SyntaxNode {
+        SyntaxNode {
+            kind: SyntaxKind::Program,
+            children: self.children,
+        }
+    }
+}
+
+/// A typed view of a [`SyntaxNode`] of one particular kind.
+pub trait AstNode<'a>: Sized {
+    fn cast(node: &'a SyntaxNode) -> Option<Self>;
+    fn syntax(&self) -> &'a SyntaxNode;
+}
+
+macro_rules! ast_node {
+    ($name:ident, $kind:ident) => {
+        #[derive(Debug, Clone, Copy)]
+        pub struct $name<'a>(&'a SyntaxNode);
+
+        impl<'a> AstNode<'a> for $name<'a> {
+            fn cast(node: &'a SyntaxNode) -> Option<Self> {
+                (node.kind == SyntaxKind::$kind).then_some($name(node))
+            }
+
+            fn syntax(&self) -> &'a SyntaxNode {
+                self.0
+            }
+        }
+    };
+}
+
+ast_node!(Program, Program);
+ast_node!(ClassDecl, ClassDecl);
+ast_node!(FunDecl, FunDecl);
+ast_node!(VarDecl, VarDecl);
+ast_node!(Block, Block);
+ast_node!(BinaryExpr, BinaryExpr);
+ast_node!(CallExpr, CallExpr);
+
+fn identifiers(node: &SyntaxNode) -> impl Iterator<Item = &Token> {
+    node.child_tokens()
+        .filter(|token| matches!(token.literal, Some(Literal::Identifier(_))))
+}
+
+fn cast_children<'a, N: AstNode<'a>>(node: &'a SyntaxNode) -> impl Iterator<Item = N> {
+    node.child_nodes().filter_map(N::cast)
+}
+
+impl<'a> Program<'a> {
+    /// The top-level declarations and statements, including any that failed
+    /// to parse.
+    pub fn statements(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0.child_nodes()
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = FunDecl<'a>> {
+        cast_children(self.0)
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = ClassDecl<'a>> {
+        cast_children(self.0)
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = VarDecl<'a>> {
+        cast_children(self.0)
+    }
+}
+
+impl<'a> ClassDecl<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        identifiers(self.0).next()
+    }
+
+    pub fn superclass(&self) -> Option<&'a Token> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::VariableExpr)
+            .and_then(|node| node.first_token())
+    }
+
+    pub fn methods(&self) -> impl Iterator<Item = FunDecl<'a>> {
+        cast_children(self.0)
+    }
+}
+
+impl<'a> FunDecl<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        identifiers(self.0).next()
+    }
+
+    pub fn params(&self) -> Vec<&'a Token> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::ParamList)
+            .map(|list| identifiers(list).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn body(&self) -> Option<Block<'a>> {
+        cast_children(self.0).next()
+    }
+}
+
+impl<'a> VarDecl<'a> {
+    pub fn name(&self) -> Option<&'a Token> {
+        identifiers(self.0).next()
+    }
+
+    pub fn initializer(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().next()
+    }
+}
+
+impl<'a> Block<'a> {
+    pub fn statements(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0.child_nodes()
+    }
+}
+
+impl<'a> BinaryExpr<'a> {
+    pub fn left(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().next()
+    }
+
+    pub fn operator(&self) -> Option<&'a Token> {
+        self.0.child_tokens().next()
+    }
+
+    pub fn right(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().nth(1)
+    }
+}
+
+impl<'a> CallExpr<'a> {
+    pub fn callee(&self) -> Option<&'a SyntaxNode> {
+        self.0.child_nodes().next()
+    }
+
+    pub fn arguments(&self) -> impl Iterator<Item = &'a SyntaxNode> {
+        self.0
+            .child_nodes()
+            .find(|node| node.kind == SyntaxKind::ArgList)
+            .into_iter()
+            .flat_map(|list| list.child_nodes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trips_source_byte_for_byte() {
+        let source = "// greeting\r\nvar  a = \"hi ${ 1 + 2 }\"; /* block\n comment */\n\
+                      fun f(x,y) { return x  +y; }\n\
+                      print (a; @ print 1;\n\
+                      class B < A { m() { this.x = super.m(); } }\n  // trailing";
+        let (root, errors) = parse(source);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(root.to_string(), source);
+        let kinds: Vec<_> = root.child_nodes().map(|node| node.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SyntaxKind::VarDecl,
+                SyntaxKind::FunDecl,
+                SyntaxKind::Error,
+                SyntaxKind::PrintStmt,
+                SyntaxKind::ClassDecl,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typed_view_reads_declarations_and_doc_comments() {
+        let source = "/// Adds two numbers.\nfun add(a, b) { return a + b; }\n\
+                      class Pair < Base { first() {} second() {} }\n\
+                      var total = add(1, 2);";
+        let (root, errors) = parse(source);
+        assert!(errors.is_empty());
+        let program = Program::cast(&root).unwrap();
+
+        let add = program.functions().next().unwrap();
+        assert_eq!(add.name().unwrap().lexeme, "add");
+        let params: Vec<_> = add.params().iter().map(|p| p.lexeme.as_str()).collect();
+        assert_eq!(params, vec!["a", "b"]);
+        assert_eq!(
+            add.syntax().leading_comments(),
+            vec!["/// Adds two numbers."]
+        );
+        let body = add.body().unwrap().statements().next().unwrap();
+        assert_eq!(body.to_string(), " return a + b;");
+
+        let pair = program.classes().next().unwrap();
+        assert_eq!(pair.superclass().unwrap().lexeme, "Base");
+        let methods: Vec<_> = pair
+            .methods()
+            .map(|m| m.name().unwrap().lexeme.clone())
+            .collect();
+        assert_eq!(methods, vec!["first", "second"]);
+
+        let total = program.variables().next().unwrap();
+        let call = CallExpr::cast(total.initializer().unwrap()).unwrap();
+        assert_eq!(call.callee().unwrap().to_string(), " add");
+        assert_eq!(call.arguments().count(), 2);
+    }
+}
@@ -4,9 +4,19 @@ use std::fmt;
 pub enum Literal {
     Identifier(String),
     String(String),
+    /// A string literal containing `${...}` segments.
+    Interpolation(Vec<StringPart>),
     Number(f64),
 }
 
+/// A piece of an interpolated string: literal text, or the tokens of an
+/// embedded expression ending in `Eof`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart {
+    Text(String),
+    Code(Vec<Token>),
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     // Single-character tokens.
@@ -37,9 +47,11 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Catch,
     Class,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
@@ -49,18 +61,57 @@ pub enum TokenType {
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
     Eof,
 }
 
-#[derive(Debug, Clone)]
+/// A half-open byte range into the interpreter's source map.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// Source text that carries no meaning for the parser. It is only kept when
+/// scanning in lossless mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+    /// Characters that could not be scanned into a token.
+    Skipped,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<Literal>,
     pub line: usize,
+    pub column: usize,
+    pub span: Span,
+    /// The trivia between the previous token and this one. Always empty
+    /// unless the scanner is lossless.
+    pub leading_trivia: Vec<Trivia>,
 }
 
 impl fmt::Display for TokenType {
@@ -80,6 +131,19 @@ impl fmt::Display for Literal {
             Literal::String(s) => {
                 write!(f, "{}", s)
             }
+            Literal::Interpolation(parts) => {
+                for part in parts {
+                    match part {
+                        StringPart::Text(s) => write!(f, "{}", s)?,
+                        StringPart::Code(tokens) => {
+                            let lexemes: Vec<&str> =
+                                tokens.iter().map(|token| token.lexeme.as_str()).collect();
+                            write!(f, "${{{}}}", lexemes.join(" ").trim_end())?
+                        }
+                    }
+                }
+                Ok(())
+            }
             Literal::Number(n) => {
                 write!(f, "{}", n)
             }
@@ -93,25 +157,26 @@ impl Token {
         lexeme: String,
         literal: Option<Literal>,
         line: usize,
+        column: usize,
+        span: Span,
     ) -> Token {
         Token {
             token_type,
             lexeme,
             literal,
             line,
+            column,
+            span,
+            leading_trivia: Vec::new(),
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self.literal {
-            Some(literal) => {
-                self.token_type.to_string()
-                    + " "
-                    + &self.lexeme
-                    + " "
-                    + literal.clone().to_string().as_str()
-            }
-            None => self.token_type.to_string() + " " + &self.lexeme,
+            Some(literal) => write!(f, "{} {} {}", self.token_type, self.lexeme, literal),
+            None => write!(f, "{} {}", self.token_type, self.lexeme),
         }
     }
 }
@@ -0,0 +1,87 @@
+use crate::interpreter::LoxValue;
+use std::fmt;
+
+/// A value passed between a script and its host. Functions, classes and
+/// instances belong to the backend that created them, so the host only sees
+/// them as opaque [`Object`]s.
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    Number(f64),
+    String(String),
+    Object(Object),
+}
+
+/// A function, class or instance. Two objects are equal only if they are the
+/// same object.
+#[derive(Clone, PartialEq)]
+pub struct Object(LoxValue);
+
+impl Object {
+    /// What kind of object this is: `"function"`, `"class"` or `"instance"`.
+    pub fn type_name(&self) -> &'static str {
+        match self.0 {
+            LoxValue::Class(_) => "class",
+            LoxValue::Instance(_) => "instance",
+            _ => "function",
+        }
+    }
+}
+
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<LoxValue> for Value {
+    fn from(value: LoxValue) -> Self {
+        match value {
+            LoxValue::Nil => Value::Nil,
+            LoxValue::Boolean(b) => Value::Boolean(b),
+            LoxValue::Number(n) => Value::Number(n),
+            LoxValue::String(s) => Value::String(s),
+            object => Value::Object(Object(object)),
+        }
+    }
+}
+
+impl From<Value> for LoxValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Nil => LoxValue::Nil,
+            Value::Boolean(b) => LoxValue::Boolean(b),
+            Value::Number(n) => LoxValue::Number(n),
+            Value::String(s) => LoxValue::String(s),
+            Value::Object(Object(object)) => object,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Object(o) => write!(f, "{}", o),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{:?}", s),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
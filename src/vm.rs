@@ -0,0 +1,753 @@
+use crate::chunk::{Constant, Function, OpCode};
+use crate::disassembler;
+use crate::error_code::ErrorCode;
+use crate::interpreter::LoxValue;
+use crate::lox_callable::{Arity, LoxCallable};
+use crate::lox_class::{LoxClass, Method};
+use crate::lox_instance::LoxInstance;
+use crate::native_function::{self, NativeFn, NativeFunction};
+use crate::runtime_error::{CallFrame, RuntimeError};
+use crate::token::{Token, TokenType};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// How deeply calls may nest before a script is stopped with a catchable
+/// error. Frames live on the heap rather than the host's stack, so this only
+/// keeps runaway recursion from exhausting memory.
+const FRAMES_MAX: usize = 16 * 1024;
+
+/// A compiled function together with the variables it captured.
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<fn {}>", self.function.name)
+    }
+}
+
+/// A captured variable. It points into the stack while the variable's scope
+/// is active and owns the value once the scope has ended.
+pub enum Upvalue {
+    Open(usize),
+    Closed(LoxValue),
+}
+
+pub struct BoundMethod {
+    pub receiver: Rc<RefCell<LoxInstance>>,
+    pub method: Rc<Closure>,
+}
+
+impl fmt::Display for BoundMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.method)
+    }
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// The stack slot holding the callee, or the receiver of a method.
+    base: usize,
+    /// The class being instantiated, if this frame runs its initializer.
+    class: Option<Rc<LoxClass>>,
+}
+
+impl Frame {
+    fn name(&self) -> &str {
+        match &self.class {
+            Some(class) => &class.name,
+            None => &self.closure.function.name,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HandlerKind {
+    Catch,
+    Finally,
+}
+
+/// Where to resume when an error is raised inside a `try` statement.
+struct Handler {
+    kind: HandlerKind,
+    frames: usize,
+    height: usize,
+    ip: usize,
+}
+
+/// Runs functions produced by the bytecode compiler.
+pub struct Vm {
+    stack: Vec<LoxValue>,
+    frames: Vec<Frame>,
+    globals: HashMap<String, LoxValue>,
+    /// Upvalues still pointing into the stack, ordered by slot.
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    handlers: Vec<Handler>,
+    /// Errors set aside while their `finally` block runs, with the slot of
+    /// the placeholder standing in for each.
+    pending: Vec<(usize, RuntimeError)>,
+    /// The class of the values `catch` binds for the VM's own errors.
+    error_class: Rc<LoxClass>,
+    output: Box<dyn Write>,
+    trace_execution: bool,
+    dump_bytecode: bool,
+}
+
+impl Vm {
+    pub fn new() -> Vm {
+        let mut vm = Vm {
+            stack: Vec::new(),
+            frames: Vec::new(),
+            globals: HashMap::new(),
+            open_upvalues: Vec::new(),
+            handlers: Vec::new(),
+            pending: Vec::new(),
+            error_class: Rc::new(LoxClass::new(String::from("Error"), None, HashMap::new())),
+            output: Box::new(io::stdout()),
+            trace_execution: false,
+            dump_bytecode: false,
+        };
+
+        vm.define_native("clock", Arity::Fixed(0), Box::new(native_function::clock));
+        vm
+    }
+
+    /// Redirects what `print` statements write.
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn set_trace_execution(&mut self, trace_execution: bool) {
+        self.trace_execution = trace_execution;
+    }
+
+    pub fn set_dump_bytecode(&mut self, dump_bytecode: bool) {
+        self.dump_bytecode = dump_bytecode;
+    }
+
+    pub fn define_native(&mut self, name: &str, arity: Arity, function: Box<NativeFn>) {
+        let native = NativeFunction::new(name, arity, function);
+        self.globals
+            .insert(name.to_string(), LoxValue::Callable(Rc::new(native)));
+    }
+
+    /// Runs a compiled script and returns what it returns.
+    pub fn interpret(&mut self, function: Function) -> Result<LoxValue, RuntimeError> {
+        let closure = Rc::new(Closure {
+            function: Rc::new(function),
+            upvalues: Vec::new(),
+        });
+        self.stack.push(LoxValue::Closure(closure.clone()));
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base: 0,
+            class: None,
+        });
+
+        let result = self.dump().and_then(|()| self.run());
+        if result.is_err() {
+            self.close_upvalues(0);
+            self.stack.clear();
+            self.frames.clear();
+            self.handlers.clear();
+            self.pending.clear();
+        }
+        result
+    }
+
+    fn dump(&mut self) -> Result<(), RuntimeError> {
+        if self.dump_bytecode {
+            let listing = disassembler::disassemble(&self.frame().closure.function);
+            self.write_output(&format!("{}\n", listing))?;
+        }
+        Ok(())
+    }
+
+    fn run(&mut self) -> Result<LoxValue, RuntimeError> {
+        loop {
+            match self.execute() {
+                Ok(value) => return Ok(value),
+                Err(err) => self.catch(err)?,
+            }
+        }
+    }
+
+    /// Hands `err` to the innermost handler, or gives it back if there is
+    /// none.
+    fn catch(&mut self, mut err: RuntimeError) -> Result<(), RuntimeError> {
+        if err.trace.is_empty() {
+            err.trace = self.trace();
+        }
+
+        let Some(handler) = self.handlers.pop() else {
+            return Err(err);
+        };
+
+        self.frames.truncate(handler.frames);
+        self.close_upvalues(handler.height);
+        self.stack.truncate(handler.height);
+        self.pending.retain(|(slot, _)| *slot < handler.height);
+        self.frame_mut().ip = handler.ip;
+
+        match handler.kind {
+            HandlerKind::Catch => {
+                let value = err.into_value(self.error_class.clone());
+                self.stack.push(value);
+            }
+            HandlerKind::Finally => {
+                self.stack.push(LoxValue::Nil);
+                self.pending.push((handler.height, err));
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self) -> Result<LoxValue, RuntimeError> {
+        loop {
+            if self.trace_execution {
+                self.trace_instruction()?;
+            }
+
+            let byte = self.read_byte();
+            let op = OpCode::from_byte(byte).expect("invalid opcode");
+
+            match op {
+                OpCode::Constant => {
+                    let value = match self.read_constant() {
+                        Constant::Number(n) => LoxValue::Number(n),
+                        Constant::String(s) => LoxValue::String(s.to_string()),
+                        Constant::Function(_) => unreachable!("functions are loaded by Closure"),
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::Nil => self.stack.push(LoxValue::Nil),
+                OpCode::True => self.stack.push(LoxValue::Boolean(true)),
+                OpCode::False => self.stack.push(LoxValue::Boolean(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::GetLocal => {
+                    let slot = self.frame().base + self.read_u16() as usize;
+                    self.stack.push(self.stack[slot].clone());
+                }
+                OpCode::SetLocal => {
+                    let slot = self.frame().base + self.read_u16() as usize;
+                    self.stack[slot] = self.peek(0).clone();
+                }
+                OpCode::GetGlobal => {
+                    let name = self.read_string();
+                    match self.globals.get(&*name) {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(self.undefined_variable(&name)),
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let name = self.read_string();
+                    let value = self.pop();
+                    self.globals.insert(name.to_string(), value);
+                }
+                OpCode::SetGlobal => {
+                    let name = self.read_string();
+                    let value = self.peek(0).clone();
+                    match self.globals.get_mut(&*name) {
+                        Some(slot) => *slot = value,
+                        None => return Err(self.undefined_variable(&name)),
+                    }
+                }
+                OpCode::GetUpvalue => {
+                    let index = self.read_u16() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = match &*upvalue.borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::SetUpvalue => {
+                    let index = self.read_u16() as usize;
+                    let upvalue = self.frame().closure.upvalues[index].clone();
+                    let value = self.peek(0).clone();
+                    match &mut *upvalue.borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+                }
+                OpCode::GetProperty => {
+                    let name = self.read_string();
+                    let LoxValue::Instance(instance) = self.pop() else {
+                        return Err(
+                            self.error(ErrorCode::NotAnInstance, "Only instances have properties.")
+                        );
+                    };
+                    match LoxInstance::property(&instance, &name) {
+                        Some(value) => self.stack.push(value),
+                        None => return Err(self.undefined_property(&name)),
+                    }
+                }
+                OpCode::SetProperty => {
+                    let name = self.read_string();
+                    let value = self.pop();
+                    // CheckInstance has run unless the code was not compiled
+                    // from source.
+                    let LoxValue::Instance(instance) = self.pop() else {
+                        return Err(
+                            self.error(ErrorCode::NotAnInstance, "Only instances have fields.")
+                        );
+                    };
+                    instance.borrow_mut().set_field(&name, value.clone());
+                    self.stack.push(value);
+                }
+                OpCode::CheckInstance => {
+                    if !matches!(self.peek(0), LoxValue::Instance(_)) {
+                        return Err(
+                            self.error(ErrorCode::NotAnInstance, "Only instances have fields.")
+                        );
+                    }
+                }
+                OpCode::GetSuper => {
+                    let name = self.read_string();
+                    let (LoxValue::Instance(object), LoxValue::Class(superclass)) =
+                        self.pop_operands()
+                    else {
+                        return Err(self
+                            .error(ErrorCode::SuperclassNotClass, "Superclass must be a class."));
+                    };
+                    match superclass.find_method(&name) {
+                        Some(method) => self.stack.push(method.bind(object)),
+                        None => return Err(self.undefined_property(&name)),
+                    }
+                }
+                OpCode::Equal => {
+                    let (left, right) = self.pop_operands();
+                    self.stack.push(LoxValue::Boolean(left == right));
+                }
+                OpCode::Greater => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(LoxValue::Boolean(left > right));
+                }
+                OpCode::GreaterEqual => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(LoxValue::Boolean(left >= right));
+                }
+                OpCode::Less => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(LoxValue::Boolean(left < right));
+                }
+                OpCode::LessEqual => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(LoxValue::Boolean(left <= right));
+                }
+                OpCode::Add => {
+                    let value = match self.pop_operands() {
+                        (LoxValue::Number(left), LoxValue::Number(right)) => {
+                            LoxValue::Number(left + right)
+                        }
+                        (LoxValue::String(left), LoxValue::String(right)) => {
+                            LoxValue::String(left + &right)
+                        }
+                        _ => {
+                            return Err(self.error(
+                                ErrorCode::OperandType,
+                                "Operands must be two numbers or two strings.",
+                            ));
+                        }
+                    };
+                    self.stack.push(value);
+                }
+                OpCode::Subtract => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(left - right);
+                }
+                OpCode::Multiply => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(left * right);
+                }
+                OpCode::Divide => {
+                    let (left, right) = self.number_operands()?;
+                    self.stack.push(left / right);
+                }
+                OpCode::Not => {
+                    let value = self.pop();
+                    self.stack.push(!value);
+                }
+                OpCode::Negate => {
+                    if !matches!(self.peek(0), LoxValue::Number(_)) {
+                        return Err(self.error(ErrorCode::OperandType, "Operand must be number."));
+                    }
+                    let value = self.pop();
+                    self.stack.push(-value);
+                }
+                OpCode::Interpolate => {
+                    let count = self.read_u16() as usize;
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    let string = parts.iter().map(|part| part.to_string()).collect();
+                    self.stack.push(LoxValue::String(string));
+                }
+                OpCode::Print => {
+                    let value = self.pop();
+                    self.write_output(&format!("{}\n", value))?;
+                }
+                OpCode::Jump => {
+                    let distance = self.read_u16() as usize;
+                    self.frame_mut().ip += distance;
+                }
+                OpCode::JumpIfFalse => {
+                    let distance = self.read_u16() as usize;
+                    if !self.peek(0).is_truthy() {
+                        self.frame_mut().ip += distance;
+                    }
+                }
+                OpCode::Loop => {
+                    let distance = self.read_u16() as usize;
+                    self.frame_mut().ip -= distance;
+                }
+                OpCode::Call => {
+                    let count = self.read_byte() as usize;
+                    self.call_value(count)?;
+                }
+                OpCode::Closure => {
+                    let Constant::Function(function) = self.read_constant() else {
+                        unreachable!()
+                    };
+                    let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                    for _ in 0..function.upvalue_count {
+                        let is_local = self.read_byte() == 1;
+                        let index = self.read_u16() as usize;
+                        let upvalue = if is_local {
+                            self.capture_upvalue(self.frame().base + index)
+                        } else {
+                            self.frame().closure.upvalues[index].clone()
+                        };
+                        upvalues.push(upvalue);
+                    }
+                    let closure = Closure { function, upvalues };
+                    self.stack.push(LoxValue::Closure(Rc::new(closure)));
+                }
+                OpCode::CloseUpvalue => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+                OpCode::Return => {
+                    let value = self.pop();
+                    let frame = self.frames.pop().expect("no frame to return from");
+                    self.close_upvalues(frame.base);
+                    self.pending.retain(|(slot, _)| *slot < frame.base);
+                    self.stack.truncate(frame.base);
+
+                    if self.frames.is_empty() {
+                        return Ok(value);
+                    }
+                    self.stack.push(value);
+                }
+                OpCode::CheckSuperclass => {
+                    if !matches!(self.pop(), LoxValue::Class(_)) {
+                        return Err(self
+                            .error(ErrorCode::SuperclassNotClass, "Superclass must be a class."));
+                    }
+                }
+                OpCode::Class => {
+                    let name = self.read_string();
+                    let count = self.read_u16() as usize;
+                    let has_superclass = self.read_byte() == 1;
+
+                    let closures = self.stack.split_off(self.stack.len() - count);
+                    let superclass = match self.stack.last() {
+                        Some(LoxValue::Class(superclass)) if has_superclass => {
+                            Some(superclass.clone())
+                        }
+                        _ => None,
+                    };
+                    let mut methods = HashMap::new();
+                    for closure in closures {
+                        let LoxValue::Closure(closure) = closure else {
+                            unreachable!()
+                        };
+                        methods.insert(closure.function.name.to_string(), Method::Closure(closure));
+                    }
+
+                    let class = LoxClass::new(name.to_string(), superclass, methods);
+                    self.stack.push(LoxValue::Class(Rc::new(class)));
+                }
+                OpCode::Throw => {
+                    let value = self.pop();
+                    return Err(RuntimeError::thrown(self.location(self.frame()), value));
+                }
+                OpCode::PushCatch | OpCode::PushFinally => {
+                    let distance = self.read_u16() as usize;
+                    let kind = if op == OpCode::PushCatch {
+                        HandlerKind::Catch
+                    } else {
+                        HandlerKind::Finally
+                    };
+                    self.handlers.push(Handler {
+                        kind,
+                        frames: self.frames.len(),
+                        height: self.stack.len(),
+                        ip: self.frame().ip + distance,
+                    });
+                }
+                OpCode::PopHandler => {
+                    self.handlers.pop();
+                }
+                OpCode::Rethrow => {
+                    let (_, err) = self.pending.pop().expect("no error to raise again");
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    /// Prints the stack, then the instruction about to run.
+    fn trace_instruction(&mut self) -> Result<(), RuntimeError> {
+        let mut line = String::from("          ");
+        for value in &self.stack {
+            line.push_str(&format!("[ {:?} ]", value));
+        }
+        line.push('\n');
+
+        let frame = self.frame();
+        disassembler::disassemble_instruction(&frame.closure.function.chunk, frame.ip, &mut line);
+        self.write_output(&line)
+    }
+
+    fn write_output(&mut self, text: &str) -> Result<(), RuntimeError> {
+        self.output
+            .write_all(text.as_bytes())
+            .map_err(|err| RuntimeError::output(self.location(self.frame()), &err))
+    }
+
+    /// Calls the value beneath the top `count` values, which are its
+    /// arguments.
+    fn call_value(&mut self, count: usize) -> Result<(), RuntimeError> {
+        let slot = self.stack.len() - count - 1;
+        let callee = self.stack[slot].clone();
+        match &callee {
+            LoxValue::Closure(closure) => self.call(closure.clone(), count, None),
+            LoxValue::BoundMethod(bound) => {
+                self.stack[slot] = LoxValue::Instance(bound.receiver.clone());
+                self.call(bound.method.clone(), count, None)
+            }
+            LoxValue::Class(class) => {
+                let instance = LoxInstance::new(class.clone());
+                self.stack[slot] = LoxValue::Instance(Rc::new(RefCell::new(instance)));
+                match class.find_method("init") {
+                    Some(Method::Closure(initializer)) => {
+                        self.call(initializer, count, Some(class.clone()))
+                    }
+                    _ if count != 0 => Err(self.arity_mismatch(0, count)),
+                    _ => self.check_depth(),
+                }
+            }
+            LoxValue::Callable(callable) if let Some(native) = callable.as_native() => {
+                if let Arity::Fixed(arity) = native.arity()
+                    && arity != count
+                {
+                    return Err(self.arity_mismatch(arity, count));
+                }
+                self.check_depth()?;
+
+                let arguments = self.stack.split_off(slot + 1);
+                let call_site = self.location(self.frame());
+                match native.invoke(&call_site, &arguments) {
+                    Ok(value) => {
+                        self.stack.pop();
+                        self.stack.push(value);
+                        Ok(())
+                    }
+                    Err(mut err) => {
+                        // Natives get no frame of their own, so add theirs.
+                        let frame = CallFrame {
+                            function: native.name().to_string(),
+                            call_site,
+                        };
+                        err.trace = std::iter::once(frame).chain(self.trace()).collect();
+                        Err(err)
+                    }
+                }
+            }
+            _ => Err(self.error(
+                ErrorCode::NotCallable,
+                "Can only call functions and classes.",
+            )),
+        }
+    }
+
+    fn call(
+        &mut self,
+        closure: Rc<Closure>,
+        count: usize,
+        class: Option<Rc<LoxClass>>,
+    ) -> Result<(), RuntimeError> {
+        if closure.function.arity != count {
+            return Err(self.arity_mismatch(closure.function.arity, count));
+        }
+        self.check_depth()?;
+
+        self.frames.push(Frame {
+            closure,
+            ip: 0,
+            base: self.stack.len() - count - 1,
+            class,
+        });
+        Ok(())
+    }
+
+    /// Refuses a call that would nest deeper than [`FRAMES_MAX`]. The script's
+    /// own frame is not a call, so it does not count.
+    fn check_depth(&self) -> Result<(), RuntimeError> {
+        if self.frames.len() > FRAMES_MAX {
+            return Err(self.error(ErrorCode::StackOverflow, "Stack overflow."));
+        }
+        Ok(())
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        let position = self
+            .open_upvalues
+            .iter()
+            .position(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(s) if s >= slot));
+        if let Some(position) = position
+            && matches!(*self.open_upvalues[position].borrow(), Upvalue::Open(s) if s == slot)
+        {
+            return self.open_upvalues[position].clone();
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        let position = position.unwrap_or(self.open_upvalues.len());
+        self.open_upvalues.insert(position, upvalue.clone());
+        upvalue
+    }
+
+    /// Moves the values of captured variables at or above `slot` off the
+    /// stack and into their upvalues.
+    fn close_upvalues(&mut self, slot: usize) {
+        while let Some(upvalue) = self.open_upvalues.last() {
+            let Upvalue::Open(open) = *upvalue.borrow() else {
+                unreachable!()
+            };
+            if open < slot {
+                break;
+            }
+
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[open].clone());
+            self.open_upvalues.pop();
+        }
+    }
+
+    /// The calls in progress, innermost first, leaving out the script.
+    fn trace(&self) -> Vec<CallFrame> {
+        self.frames
+            .windows(2)
+            .rev()
+            .map(|frames| CallFrame {
+                function: frames[1].name().to_string(),
+                call_site: self.location(&frames[0]),
+            })
+            .collect()
+    }
+
+    /// A token standing in for the code `frame` is executing.
+    fn location(&self, frame: &Frame) -> Token {
+        let chunk = &frame.closure.function.chunk;
+        // Before the first instruction has been read, report that one.
+        let offset = frame.ip.saturating_sub(1);
+        Token::new(
+            TokenType::Eof,
+            String::new(),
+            None,
+            chunk.lines[offset],
+            0,
+            chunk.spans[offset],
+        )
+    }
+
+    fn error(&self, code: ErrorCode, message: &str) -> RuntimeError {
+        RuntimeError::new(self.location(self.frame()), code, message)
+    }
+
+    fn undefined_variable(&self, name: &str) -> RuntimeError {
+        self.error(
+            ErrorCode::UndefinedVariable,
+            &format!("Undefined variable '{}'.", name),
+        )
+    }
+
+    fn undefined_property(&self, name: &str) -> RuntimeError {
+        self.error(
+            ErrorCode::UndefinedProperty,
+            &format!("Undefined property '{}'.", name),
+        )
+    }
+
+    fn arity_mismatch(&self, arity: usize, count: usize) -> RuntimeError {
+        self.error(
+            ErrorCode::ArityMismatch,
+            &format!("Expected {} arguments but got {}.", arity, count),
+        )
+    }
+
+    fn pop_operands(&mut self) -> (LoxValue, LoxValue) {
+        let right = self.pop();
+        let left = self.pop();
+        (left, right)
+    }
+
+    fn number_operands(&mut self) -> Result<(LoxValue, LoxValue), RuntimeError> {
+        if !matches!(
+            (self.peek(1), self.peek(0)),
+            (LoxValue::Number(_), LoxValue::Number(_))
+        ) {
+            return Err(self.error(ErrorCode::OperandType, "Operands must be numbers."));
+        }
+        Ok(self.pop_operands())
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("no frame is running")
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no frame is running")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.frame_mut();
+        let value = frame.closure.function.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_constant(&mut self) -> Constant {
+        let index = self.read_u16() as usize;
+        self.frame().closure.function.chunk.constants[index].clone()
+    }
+
+    fn read_string(&mut self) -> Rc<str> {
+        match self.read_constant() {
+            Constant::String(s) => s,
+            _ => unreachable!("names are string constants"),
+        }
+    }
+
+    fn pop(&mut self) -> LoxValue {
+        self.stack.pop().expect("stack underflow")
+    }
+
+    fn peek(&self, distance: usize) -> &LoxValue {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+}